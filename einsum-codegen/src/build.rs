@@ -0,0 +1,242 @@
+//! Generate einsum functions into a Rust module in build scripts
+//!
+//! Instead of expanding `einsum!` on every build, the functions are generated once by `build.rs`
+//!
+//! ```no_run
+//! // build.rs
+//! einsum_codegen::build::Builder::new()
+//!     .function("bmm", "bij,bjk->bik")
+//!     .function("matmul3", "ij,jk,kl->il")
+//!     .write("einsum.rs")
+//!     .unwrap();
+//! ```
+//!
+//! and included in the crate by `include!(concat!(env!("OUT_DIR"), "/einsum.rs"));`.
+//! The functions are the same as `einsum_fn!` of einsum-derive, see [named_function],
+//! and require `core::iter::Sum` of the element type to return zero for no elements.
+//! The module is formatted by [format_block] if `rustfmt` is available,
+//! and it can also be vendored into the source tree by [Builder::generate].
+
+#[cfg(doc)]
+use crate::codegen::ndarray::named::named_function;
+
+use crate::codegen::{
+    format_block,
+    ndarray::{naive, named},
+};
+use anyhow::{bail, Result};
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Builder of the module containing the einsum functions
+#[derive(Clone, Default)]
+pub struct Builder {
+    /// Name and subscripts of each function
+    functions: Vec<(String, String)>,
+    options: naive::Options,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a public function, e.g. `pub fn bmm` computing `bij,bjk->bik`
+    pub fn function(mut self, name: &str, subscripts: &str) -> Self {
+        self.functions
+            .push((name.to_string(), subscripts.to_string()));
+        self
+    }
+
+    /// Options of all functions, e.g. [naive::Options::unchecked]
+    pub fn options(mut self, options: naive::Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Source code of the module, where the functions are separated by empty lines
+    ///
+    /// The functions are left unformatted if `rustfmt` is not found.
+    pub fn generate(&self) -> Result<String> {
+        let rustfmt = Command::new("rustfmt")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        let mut functions = Vec::new();
+        for (name, subscripts) in &self.functions {
+            let path = crate::Path::brute_force(subscripts)?;
+            let name: syn::Ident = syn::parse_str(name)?;
+            let tt = named::named_function(
+                &syn::parse_quote! { pub },
+                &name,
+                subscripts,
+                &path,
+                &self.options,
+            )?;
+            if !rustfmt {
+                functions.push(tt.to_string());
+                continue;
+            }
+            let code = format_block(tt.to_string());
+            if code.is_empty() {
+                bail!("Failed to format the function {} by rustfmt", name);
+            }
+            functions.push(doc_comments(&code));
+        }
+        Ok(format!(
+            "// Generated by einsum-codegen. Do not edit by hand.\n\n{}\n",
+            functions.join("\n\n")
+        ))
+    }
+
+    /// Write the module into `OUT_DIR`, and returns the path of the file
+    pub fn write(&self, file_name: &str) -> Result<PathBuf> {
+        let out_dir = match env::var_os("OUT_DIR") {
+            Some(out_dir) => PathBuf::from(out_dir),
+            None => bail!("OUT_DIR is not set, Builder::write must be called in build.rs"),
+        };
+        let path = out_dir.join(file_name);
+        fs::write(&path, self.generate()?)?;
+        Ok(path)
+    }
+}
+
+/// Replace `#[doc = " ..."]` generated by `quote!` with `/// ...` to be read easily
+///
+/// The doc comments on statements following `#[allow(unused_doc_comments)]`,
+/// i.e. the safety argument of [naive::Options::unchecked], are replaced with `// ...` instead.
+fn doc_comments(code: &str) -> String {
+    let mut lines = Vec::new();
+    let mut statement = false;
+    for line in code.lines() {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        if trimmed == "#[allow(unused_doc_comments)]" {
+            statement = true;
+            continue;
+        }
+        match trimmed
+            .strip_prefix("#[doc = \"")
+            .and_then(|doc| doc.strip_suffix("\"]"))
+        {
+            // Escaped characters are kept as attributes
+            Some(doc) if !doc.contains('\\') => {
+                let slashes = if statement { "//" } else { "///" };
+                lines.push(
+                    format!("{}{}{}", indent, slashes, doc)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+            _ => {
+                statement = false;
+                lines.push(line.to_string());
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dot() {
+        let code = Builder::new().function("dot", "i,i->").generate().unwrap();
+        insta::assert_snapshot!(code, @r###"
+        // Generated by einsum-codegen. Do not edit by hand.
+
+        /// einsum `i,i->`
+        ///
+        /// Computed along the path of 1 step, which requires O(N^1) operations and O(N^0) memory:
+        ///
+        /// - `a,a-> | arg0,arg1->out0`
+        #[allow(non_snake_case, clippy::let_and_return)]
+        pub fn dot<T, S0, S1>(
+            arg0: ndarray::ArrayBase<S0, ndarray::Ix1>,
+            arg1: ndarray::ArrayBase<S1, ndarray::Ix1>,
+        ) -> T
+        where
+            T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            S0: ndarray::Data<Elem = T>,
+            S1: ndarray::Data<Elem = T>,
+        {
+            fn a_a__<T, T0, T1, S0, S1>(
+                arg0: ndarray::ArrayBase<S0, ndarray::Ix1>,
+                arg1: ndarray::ArrayBase<S1, ndarray::Ix1>,
+            ) -> ndarray::Array<T, ndarray::Ix0>
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+                T0: Clone + core::ops::Mul<T1, Output = T>,
+                T1: Clone,
+                S0: ndarray::Data<Elem = T0>,
+                S1: ndarray::Data<Elem = T1>,
+            {
+                let n_a = arg0.dim();
+                let _ = arg1.dim();
+                {
+                    let n_0 = arg0.dim();
+                    assert_eq!(n_0, n_a);
+                }
+                {
+                    let n_0 = arg1.dim();
+                    assert_eq!(n_0, n_a);
+                }
+                let mut out0 = ndarray::Array::<T, _>::from_elem(
+                    (),
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
+                let mut sum = out0[()].clone();
+                let lane0 = arg0.slice(ndarray::s![0..n_a]);
+                let lane1 = arg1.slice(ndarray::s![0..n_a]);
+                sum = ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .fold(sum, |mut sum, elem0, elem1| {
+                        sum = sum + elem0.clone() * elem1.clone();
+                        sum
+                    });
+                out0[()] = sum;
+                out0
+            }
+            let arg0 = arg0;
+            let arg1 = arg1;
+            let out0 = a_a__(arg0, arg1);
+            out0.into_scalar()
+        }
+        "###);
+    }
+
+    #[test]
+    fn unchecked_safety_comments() {
+        let code = Builder::new()
+            .function("matvec", "ij,j->i")
+            .options(naive::Options {
+                unchecked: true,
+                ..Default::default()
+            })
+            .generate()
+            .unwrap();
+        let safety: Vec<&str> = code
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("//") && !line.starts_with("///"))
+            .collect();
+        insta::assert_snapshot!(safety.join("\n"), @r###"
+        // Generated by einsum-codegen. Do not edit by hand.
+        // SAFETY: All the indices passed to `uget` and `uget_mut` in this block are in bounds:
+        // - `arg0.dim()` is `(n_a, n_b)` by `assert_eq!(n_0, n_a)`, `assert_eq!(n_1, n_b)` above
+        // - `arg1.dim()` is `n_b` by `assert_eq!(n_0, n_b)` above
+        // - `out0` is created with the shape `n_a`
+        // - The loops, tiles and flattened indices decoded by `l / stride % n` run `a` in `0..n_a`, `b` in `0..n_b`
+        "###);
+        assert!(!code.contains("unused_doc_comments"));
+    }
+
+    #[test]
+    fn invalid_name() {
+        assert!(Builder::new()
+            .function("not an ident", "i,i->")
+            .generate()
+            .is_err());
+    }
+}
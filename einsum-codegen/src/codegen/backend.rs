@@ -0,0 +1,176 @@
+//! Array libraries generating the einsum functions
+
+use crate::{Path, Subscript, Subscripts};
+use anyhow::{bail, Result};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::BTreeSet;
+
+/// Code generator for an array library
+///
+/// Each step of the [Path] is computed by a function defined by [EinsumBackend::function_definition],
+/// which is composed of the other methods by default:
+///
+/// ```ignore
+/// #signature {
+///     #helpers
+///     #array_size
+///     #alloc
+///     #kernel
+///     #output
+/// }
+/// ```
+///
+/// where the sizes of indices are bound to `n_{index}`, e.g. `n_a`,
+/// and the arrays are referred by [Position], e.g. `arg0` and `out1`.
+/// The steps are connected by [einsum] independently from the array library.
+pub trait EinsumBackend {
+    /// Signature of the function computing the step, e.g.
+    /// `fn ab_bc__ac<T, S0, S1>(arg0: ArrayBase<S0, Ix2>, arg1: ArrayBase<S1, Ix2>) -> Array2<T> where ...`
+    ///
+    /// The function name is [EinsumBackend::function_name] and the arguments are the inputs.
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2;
+
+    /// Name of the function computing the step, [Subscripts::escaped_ident] by default
+    ///
+    /// The function is defined once for each name, and shared among the steps of the same name.
+    fn function_name(&self, subscripts: &Subscripts) -> syn::Ident {
+        format_ident!("{}", subscripts.escaped_ident())
+    }
+
+    /// Whether the generated code refers `alloc` crate, e.g. `alloc::vec::Vec`,
+    /// which is declared by `extern crate alloc;` in the block of [einsum]
+    ///
+    /// The block of backends returning false works without `alloc` crate.
+    fn uses_alloc(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Items used in the function body, e.g. helper functions
+    fn helpers(&self, _subscripts: &Subscripts) -> TokenStream2 {
+        TokenStream2::new()
+    }
+
+    /// Bind the size of each index to `n_{index}`, and check the sizes of the inputs are consistent
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2;
+
+    /// Allocate the output array filled with zeros
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2;
+
+    /// Element of the array at the indices, which is a place expression if `mutable` is true
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], mutable: bool) -> TokenStream2;
+
+    /// Loops summing up the products of the inputs into the output,
+    /// which may dispatch among several loop nests at runtime
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2;
+
+    /// Definition of the function computing the step
+    fn function_definition(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let signature = self.signature(subscripts);
+        let helpers = self.helpers(subscripts);
+        let array_size = self.array_size(subscripts);
+        let alloc = self.alloc(subscripts);
+        let kernel = self.kernel(subscripts);
+        let output = &subscripts.output;
+        quote! {
+            #signature {
+                #helpers
+                #array_size
+                #alloc
+                #kernel
+                #output
+            }
+        }
+    }
+
+    /// Bind the user input to the argument of the einsum, e.g. `let arg0 = a;`
+    ///
+    /// Backends accepting arrays of the rank known only at runtime convert them here.
+    fn argument(&self, subscript: &Subscript, arg: &syn::Expr) -> TokenStream2 {
+        quote! { let #subscript = #arg; }
+    }
+
+    /// Call of the function defined by [EinsumBackend::function_definition],
+    /// e.g. `let out0 = ab_bc__ac(arg0, arg1);`
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        quote! { #subscripts }
+    }
+
+    /// Value returned to the user from the output of the last step
+    fn output(&self, output: &Subscript) -> TokenStream2 {
+        quote! { #output }
+    }
+}
+
+/// Block computing einsum along the path by the backend
+///
+/// The functions are defined once for each distinct subscripts,
+/// and the user inputs `args` are bound to `arg0`, `arg1`, and so on by [EinsumBackend::argument].
+///
+/// The generated code refers only `core` and `alloc` crates to work in `no_std` crates,
+/// e.g. `core::ops::Add` and `alloc::vec::Vec`, where `alloc` is declared in the block
+/// only if [EinsumBackend::uses_alloc] is true.
+pub fn einsum(backend: &impl EinsumBackend, path: &Path, args: &[syn::Expr]) -> TokenStream2 {
+    let arg_bindings: Vec<_> = path
+        .original()
+        .inputs
+        .iter()
+        .zip(args)
+        .map(|(ss, arg)| backend.argument(ss, arg))
+        .collect();
+    let mut defined = BTreeSet::new();
+    let fn_defs: Vec<_> = path
+        .iter()
+        .filter(|ss| defined.insert(backend.function_name(ss)))
+        .map(|ss| backend.function_definition(ss))
+        .collect();
+    let fn_calls: Vec<_> = path.iter().map(|ss| backend.function_call(ss)).collect();
+    let out_tt = backend.output(path.output());
+    let extern_alloc = backend
+        .uses_alloc(path)
+        .then(|| quote! { extern crate alloc; });
+    quote! {
+        {
+            #extern_alloc
+            #(#fn_defs)*
+            #(#arg_bindings)*
+            #(#fn_calls)*
+            #out_tt
+        }
+    }
+}
+
+/// Check that all the arrays in the steps are at most `max_rank`,
+/// e.g. 2 for backends of matrices and vectors
+pub fn check_rank<'a>(
+    steps: impl IntoIterator<Item = &'a Subscripts>,
+    max_rank: usize,
+) -> Result<()> {
+    for ss in steps {
+        for arg in ss.inputs.iter().chain(std::iter::once(&ss.output)) {
+            let rank = arg.indices().len();
+            if rank > max_rank {
+                bail!(
+                    "Arrays of rank at most {} are supported, but {} has an array of rank {}",
+                    max_rank,
+                    ss,
+                    rank
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rank() {
+        let path = Path::brute_force("ij,jk->ijk").unwrap();
+        assert!(check_rank(path.iter(), 2).is_err());
+        let path = Path::brute_force("ij,jk,kl->il").unwrap();
+        assert!(check_rank(path.iter(), 2).is_ok());
+    }
+}
@@ -0,0 +1,287 @@
+//! For [faer](https://crates.io/crates/faer) crate
+//!
+//! The operands are `MatRef<T>`, `ColRef<T>`, and `T` for rank 2, 1, and 0 respectively,
+//! and the outputs are `Mat<T>`, `Col<T>`, and `T`.
+//! The element type `T` is [faer::traits::ComplexField](https://docs.rs/faer/latest/faer/traits/trait.ComplexField.html),
+//! e.g. `f32`, `f64`, `c32`, and `c64`.
+//!
+//! The product of two matrices, e.g. `ij,jk->ik` or `ji,kj->ik`, is computed by
+//! `faer::linalg::matmul::matmul` on the transposed views if needed
+//! with the global parallelism setting of faer.
+//! The other steps are computed by the naive loops of [crate::ir].
+
+use super::{
+    print::{n_ident, strided_loops, tuple},
+    EinsumBackend,
+};
+use crate::{Subscript, Subscripts};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+
+/// [EinsumBackend] for faer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Faer;
+
+/// `faer::linalg::matmul::matmul` computing the output of two matrices,
+/// or `None` if the step is not a product of two matrices
+fn matmul(subscripts: &Subscripts) -> Option<TokenStream2> {
+    let output = subscripts.output.indices();
+    let (x, z) = match (subscripts.inputs.len(), output.as_slice()) {
+        (2, &[x, z]) if x != z => (x, z),
+        _ => return None,
+    };
+    let contraction: Vec<char> = subscripts.contraction_indices().into_iter().collect();
+    let y = match contraction.as_slice() {
+        &[y] => y,
+        _ => return None,
+    };
+    // View of the input in the order of `indices`, transposed if needed
+    let view = |arg: &Subscript, indices: [char; 2]| -> Option<TokenStream2> {
+        let [i, j] = indices;
+        match arg.indices().as_slice() {
+            [a, b] if *a == i && *b == j => Some(quote! { #arg }),
+            [a, b] if *a == j && *b == i => Some(quote! { #arg.transpose() }),
+            _ => None,
+        }
+    };
+    for (l, r) in [(0, 1), (1, 0)] {
+        let (lhs, rhs) = (&subscripts.inputs[l], &subscripts.inputs[r]);
+        if let (Some(lhs), Some(rhs)) = (view(lhs, [x, y]), view(rhs, [y, z])) {
+            let out = &subscripts.output;
+            return Some(quote! {
+                faer::linalg::matmul::matmul(
+                    #out.as_mut(),
+                    faer::Accum::Replace,
+                    #lhs,
+                    #rhs,
+                    <T as faer::traits::ComplexField>::one_impl(),
+                    faer::get_global_parallelism(),
+                );
+            });
+        }
+    }
+    None
+}
+
+/// Type of the operand or output of rank `n`
+fn array_type(n: usize, owned: bool) -> TokenStream2 {
+    match (n, owned) {
+        (0, _) => quote! { T },
+        (1, false) => quote! { faer::ColRef<'_, T> },
+        (1, true) => quote! { faer::Col<T> },
+        (_, false) => quote! { faer::MatRef<'_, T> },
+        (_, true) => quote! { faer::Mat<T> },
+    }
+}
+
+/// Shape of the column or matrix, e.g. `(arg0.nrows(), arg0.ncols())` for a matrix
+fn shape(arg: &Subscript) -> TokenStream2 {
+    match arg.indices().len() {
+        1 => quote! { #arg.nrows() },
+        _ => quote! { (#arg.nrows(), #arg.ncols()) },
+    }
+}
+
+impl EinsumBackend for Faer {
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let args = &subscripts.inputs;
+        let types = args
+            .iter()
+            .map(|arg| array_type(arg.indices().len(), false));
+        let out_ty = array_type(subscripts.output.indices().len(), true);
+        quote! {
+            fn #fn_name<T>(
+                #( #args: #types ),*
+            ) -> #out_ty
+            where
+                T: faer::traits::ComplexField + core::ops::Add<Output = T> + core::ops::Mul<Output = T>
+        }
+    }
+
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let mut appeared: HashSet<char> = HashSet::new();
+        let mut tt = Vec::new();
+        for arg in subscripts.inputs.iter() {
+            if arg.indices().iter().all(|i| appeared.contains(i)) {
+                continue;
+            }
+            let n = tuple(arg.indices().into_iter().map(|i| {
+                if appeared.insert(i) {
+                    let n = n_ident(i);
+                    quote! { #n }
+                } else {
+                    quote! { _ }
+                }
+            }));
+            let shape = shape(arg);
+            tt.push(quote! { let #n = #shape; });
+        }
+        for arg in subscripts
+            .inputs
+            .iter()
+            .filter(|arg| !arg.indices().is_empty())
+        {
+            // local variable, e.g. `n_1`
+            let n_each: Vec<_> = (0..arg.indices().len())
+                .map(|m| format_ident!("n_{}", m))
+                .collect();
+            let n_each_tuple = tuple(n_each.iter().map(|n| quote! { #n }));
+            // size of index defined previously, e.g. `n_i`
+            let n: Vec<_> = arg.indices().into_iter().map(n_ident).collect();
+            let shape = shape(arg);
+            tt.push(quote! {
+                {
+                    let #n_each_tuple = #shape;
+                    #(assert_eq!(#n_each, #n);)*
+                }
+            });
+        }
+        quote! { #(#tt)* }
+    }
+
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let output = &subscripts.output;
+        let n: Vec<syn::Ident> = subscripts
+            .output
+            .indices()
+            .into_iter()
+            .map(n_ident)
+            .collect();
+        match n.len() {
+            0 => quote! { let mut #output = <T as faer::traits::ComplexField>::zero_impl(); },
+            1 => quote! { let mut #output = faer::Col::<T>::zeros(#(#n),*); },
+            _ => quote! { let mut #output = faer::Mat::<T>::zeros(#(#n),*); },
+        }
+    }
+
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], _mutable: bool) -> TokenStream2 {
+        match indices {
+            [] => quote! { #array },
+            [i] => quote! { #array[#i] },
+            _ => quote! { #array[(#(#indices),*)] },
+        }
+    }
+
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2 {
+        if let Some(matmul) = matmul(subscripts) {
+            return matmul;
+        }
+        strided_loops(subscripts, self, true)
+    }
+
+    /// Pass the views of the arrays, e.g. `faer::mat::AsMatRef::as_mat_ref(&arg0)`
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let args = subscripts
+            .inputs
+            .iter()
+            .map(|arg| match arg.indices().len() {
+                0 => quote! { #arg },
+                1 => quote! { faer::col::AsColRef::as_col_ref(&#arg) },
+                _ => quote! { faer::mat::AsMatRef::as_mat_ref(&#arg) },
+            });
+        let out = &subscripts.output;
+        quote! {
+            let #out = #fn_name(#(#args),*);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{einsum, format_block},
+        *,
+    };
+
+    #[test]
+    fn matmul() {
+        let path = Path::brute_force("ij,kj->ik").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { b }];
+        let tt = format_block(einsum(&Faer, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn ab_cb__ac<T>(arg0: faer::MatRef<'_, T>, arg1: faer::MatRef<'_, T>) -> faer::Mat<T>
+            where
+                T: faer::traits::ComplexField + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let (n_a, n_b) = (arg0.nrows(), arg0.ncols());
+                let (n_c, _) = (arg1.nrows(), arg1.ncols());
+                {
+                    let (n_0, n_1) = (arg0.nrows(), arg0.ncols());
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                {
+                    let (n_0, n_1) = (arg1.nrows(), arg1.ncols());
+                    assert_eq!(n_0, n_c);
+                    assert_eq!(n_1, n_b);
+                }
+                let mut out0 = faer::Mat::<T>::zeros(n_a, n_c);
+                faer::linalg::matmul::matmul(
+                    out0.as_mut(),
+                    faer::Accum::Replace,
+                    arg0,
+                    arg1.transpose(),
+                    <T as faer::traits::ComplexField>::one_impl(),
+                    faer::get_global_parallelism(),
+                );
+                out0
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let out0 = ab_cb__ac(
+                faer::mat::AsMatRef::as_mat_ref(&arg0),
+                faer::mat::AsMatRef::as_mat_ref(&arg1),
+            );
+            out0
+        }
+        "###);
+    }
+
+    #[test]
+    fn loops() {
+        let path = Path::brute_force("ij,ij->").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { b }];
+        let tt = format_block(einsum(&Faer, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn ab_ab__<T>(arg0: faer::MatRef<'_, T>, arg1: faer::MatRef<'_, T>) -> T
+            where
+                T: faer::traits::ComplexField + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let (n_a, n_b) = (arg0.nrows(), arg0.ncols());
+                {
+                    let (n_0, n_1) = (arg0.nrows(), arg0.ncols());
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                {
+                    let (n_0, n_1) = (arg1.nrows(), arg1.ncols());
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                let mut out0 = <T as faer::traits::ComplexField>::zero_impl();
+                let mut sum = out0.clone();
+                for b in 0..n_b {
+                    for a in 0..n_a {
+                        sum = sum + arg0[(a, b)].clone() * arg1[(a, b)].clone();
+                    }
+                }
+                out0 = sum;
+                out0
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let out0 = ab_ab__(
+                faer::mat::AsMatRef::as_mat_ref(&arg0),
+                faer::mat::AsMatRef::as_mat_ref(&arg1),
+            );
+            out0
+        }
+        "###);
+    }
+}
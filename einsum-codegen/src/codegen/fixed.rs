@@ -0,0 +1,198 @@
+//! For nested fixed-size arrays, e.g. `[[f64; 3]; 3]`
+//!
+//! The size of each index is a const generic parameter, e.g. `N_A` for the index `a`,
+//! so that mismatched sizes are type errors, e.g. `ab,bc->ac` for
+//!
+//! ```ignore
+//! fn ab_bc__ac<T, const N_A: usize, const N_B: usize, const N_C: usize>(
+//!     arg0: &[[T; N_B]; N_A],
+//!     arg1: &[[T; N_C]; N_B],
+//! ) -> [[T; N_C]; N_A]
+//! ```
+//!
+//! The outputs including the intermediate ones live on the stack, and 0-rank arrays are `T` itself.
+//!
+//! The loops are not unrolled in the generated code.
+//! The macro only sees the expressions of operands, not their types,
+//! so the sizes are the const generic parameters unknown at expansion time,
+//! and the generated code is the ordinary `for` loops over them.
+//! Unrolling them is left to LLVM after monomorphization fixes the sizes,
+//! which is likely for small arrays in optimized builds but not guaranteed.
+
+use super::{
+    print::{n_ident, strided_loops, zero_tt},
+    EinsumBackend,
+};
+use crate::{Subscript, Subscripts};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::BTreeSet;
+
+/// [EinsumBackend] for nested fixed-size arrays
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fixed;
+
+/// Const generic parameter of the size of index, e.g. `N_A` for `a`
+fn size_param(index: char) -> syn::Ident {
+    format_ident!("N_{}", index.to_ascii_uppercase())
+}
+
+/// Nested array type, e.g. `[[T; N_B]; N_A]` for `ab`
+fn array_type(arg: &Subscript) -> TokenStream2 {
+    arg.indices()
+        .into_iter()
+        .rev()
+        .fold(quote! { T }, |ty, index| {
+            let n = size_param(index);
+            quote! { [#ty; #n] }
+        })
+}
+
+impl EinsumBackend for Fixed {
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let indices: BTreeSet<char> = subscripts
+            .inputs
+            .iter()
+            .flat_map(|arg| arg.indices())
+            .collect();
+        let sizes = indices.into_iter().map(size_param);
+        let args = &subscripts.inputs;
+        let types = args.iter().map(|arg| {
+            let ty = array_type(arg);
+            // 0-rank arrays are passed by value
+            if arg.indices().is_empty() {
+                ty
+            } else {
+                quote! { &#ty }
+            }
+        });
+        let out_ty = array_type(&subscripts.output);
+        quote! {
+            fn #fn_name<T, #(const #sizes: usize),*>(
+                #( #args: #types ),*
+            ) -> #out_ty
+            where
+                T: Copy + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>
+        }
+    }
+
+    /// Sizes are the const generic parameters checked by the types
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let indices: BTreeSet<char> = subscripts
+            .inputs
+            .iter()
+            .flat_map(|arg| arg.indices())
+            .collect();
+        let n = indices.iter().cloned().map(n_ident);
+        let sizes = indices.iter().cloned().map(size_param);
+        quote! { #(let #n = #sizes;)* }
+    }
+
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let output = &subscripts.output;
+        let zeros = subscripts.output.indices().into_iter().rev().fold(
+            zero_tt(&quote! { T }),
+            |zeros, index| {
+                let n = size_param(index);
+                quote! { [#zeros; #n] }
+            },
+        );
+        quote! { let mut #output = #zeros; }
+    }
+
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], _mutable: bool) -> TokenStream2 {
+        quote! { #array #([#indices])* }
+    }
+
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2 {
+        strided_loops(subscripts, self, false)
+    }
+
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let args = subscripts.inputs.iter().map(|arg| {
+            if arg.indices().is_empty() {
+                quote! { #arg }
+            } else {
+                quote! { &#arg }
+            }
+        });
+        let out = &subscripts.output;
+        quote! {
+            let #out = #fn_name(#(#args),*);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{einsum, format_block},
+        *,
+    };
+
+    #[test]
+    fn matmul() {
+        let path = Path::brute_force("ij,jk->ik").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { b }];
+        let tt = format_block(einsum(&Fixed, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn ab_bc__ac<T, const N_A: usize, const N_B: usize, const N_C: usize>(
+                arg0: &[[T; N_B]; N_A],
+                arg1: &[[T; N_C]; N_B],
+            ) -> [[T; N_C]; N_A]
+            where
+                T: Copy + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let n_a = N_A;
+                let n_b = N_B;
+                let n_c = N_C;
+                let mut out0 = [[<T as core::iter::Sum>::sum(core::iter::empty()); N_C]; N_A];
+                for a in 0..n_a {
+                    for b in 0..n_b {
+                        let prod0 = arg0[a][b].clone();
+                        for c in 0..n_c {
+                            out0[a][c] = out0[a][c].clone() + prod0.clone() * arg1[b][c].clone();
+                        }
+                    }
+                }
+                out0
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let out0 = ab_bc__ac(&arg0, &arg1);
+            out0
+        }
+        "###);
+    }
+
+    #[test]
+    fn trace() {
+        let path = Path::brute_force("ii->").unwrap();
+        let args = [syn::parse_quote! { a }];
+        let tt = format_block(einsum(&Fixed, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn aa__<T, const N_A: usize>(arg0: &[[T; N_A]; N_A]) -> T
+            where
+                T: Copy + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let n_a = N_A;
+                let mut out0 = <T as core::iter::Sum>::sum(core::iter::empty());
+                let mut sum = out0.clone();
+                for a in 0..n_a {
+                    sum = sum + arg0[a][a].clone();
+                }
+                out0 = sum;
+                out0
+            }
+            let arg0 = a;
+            let out0 = aa__(&arg0);
+            out0
+        }
+        "###);
+    }
+}
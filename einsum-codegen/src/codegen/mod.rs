@@ -1,6 +1,14 @@
 //! Generate einsum implementation
 
+mod backend;
 mod format;
+mod print;
+pub use backend::*;
 pub use format::format_block;
 
+pub mod faer;
+pub mod fixed;
+pub mod nalgebra;
 pub mod ndarray;
+pub mod runtime;
+pub mod slice;
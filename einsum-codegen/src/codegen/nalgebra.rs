@@ -0,0 +1,350 @@
+//! For [nalgebra](https://crates.io/crates/nalgebra) crate
+//!
+//! The operands are matrices, column vectors, and 1x1 matrices for rank 2, 1, and 0 respectively,
+//! which may have fixed sizes, e.g. `SMatrix` and `SVector`, or be views.
+//! All the operands have the same element type `T`, and the output is `DMatrix<T>`, `DVector<T>`,
+//! or `T` for rank 2, 1, and 0 respectively.
+//!
+//! The product of two matrices is computed by `gemm` if it can be written as `a * b` or `a^T * b`,
+//! otherwise the naive loops of [crate::ir] are generated.
+//! `gemm` requires `num_traits::Zero` and `num_traits::One` of the elements,
+//! so the user crate has to depend on `num-traits` for such products.
+
+use super::{
+    print::{n_ident, strided_loops, tuple, zero_tt},
+    EinsumBackend,
+};
+use crate::{Subscript, Subscripts};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+
+/// [EinsumBackend] for nalgebra
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nalgebra;
+
+/// `a.gemm(...)` or `a.gemm_tr(...)` computing the output of two matrices,
+/// or `None` if the product is not written by them
+fn gemm(subscripts: &Subscripts) -> Option<TokenStream2> {
+    let output = subscripts.output.indices();
+    let (x, z) = match (subscripts.inputs.len(), output.as_slice()) {
+        (2, &[x, z]) if x != z => (x, z),
+        _ => return None,
+    };
+    for (l, r) in [(0, 1), (1, 0)] {
+        let (lhs, rhs) = (&subscripts.inputs[l], &subscripts.inputs[r]);
+        let y = match rhs.indices().as_slice() {
+            &[y, rz] if rz == z && y != x && y != z => y,
+            _ => continue,
+        };
+        let method = match lhs.indices().as_slice() {
+            [lx, ly] if *lx == x && *ly == y => quote! { gemm },
+            [ly, lx] if *lx == x && *ly == y => quote! { gemm_tr },
+            _ => continue,
+        };
+        let out = &subscripts.output;
+        // Views have dynamic sizes satisfying the shape constraints of `gemm`
+        return Some(quote! {
+            #out.#method(
+                <T as num_traits::One>::one(),
+                &#lhs.view((0, 0), #lhs.shape()),
+                &#rhs.view((0, 0), #rhs.shape()),
+                <T as num_traits::Zero>::zero(),
+            );
+        });
+    }
+    None
+}
+
+/// Type of the array with `n` index, and its generic parameters
+fn array_type(n: usize, k: usize) -> (TokenStream2, Vec<syn::Ident>) {
+    let s = format_ident!("S{}", k);
+    let r = format_ident!("R{}", k);
+    let c = format_ident!("C{}", k);
+    match n {
+        0 => (
+            quote! { nalgebra::Matrix<T, nalgebra::U1, nalgebra::U1, #s> },
+            vec![s],
+        ),
+        1 => (
+            quote! { nalgebra::Matrix<T, #r, nalgebra::U1, #s> },
+            vec![r, s],
+        ),
+        _ => (quote! { nalgebra::Matrix<T, #r, #c, #s> }, vec![r, c, s]),
+    }
+}
+
+/// Shape of the vector or matrix, e.g. `(n_a, n_b)` for a matrix
+fn shape(arg: &Subscript) -> TokenStream2 {
+    match arg.indices().len() {
+        1 => quote! { #arg.nrows() },
+        _ => quote! { #arg.shape() },
+    }
+}
+
+impl EinsumBackend for Nalgebra {
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let args = &subscripts.inputs;
+        let mut generics = Vec::new();
+        let mut types = Vec::new();
+        let mut predicates = Vec::new();
+        for (k, arg) in args.iter().enumerate() {
+            let (ty, params) = array_type(arg.indices().len(), k);
+            let (dims, storage) = params.split_at(params.len() - 1);
+            let storage = &storage[0];
+            let (r, c) = match dims {
+                [] => (quote! { nalgebra::U1 }, quote! { nalgebra::U1 }),
+                [r] => (quote! { #r }, quote! { nalgebra::U1 }),
+                [r, c] => (quote! { #r }, quote! { #c }),
+                _ => unreachable!(),
+            };
+            for d in dims {
+                predicates.push(quote! { #d: nalgebra::Dim });
+            }
+            predicates.push(quote! { #storage: nalgebra::RawStorage<T, #r, #c> });
+            generics.extend(params);
+            types.push(ty);
+        }
+        let out_ty = match subscripts.output.indices().len() {
+            0 => quote! { nalgebra::Matrix1<T> },
+            1 => quote! { nalgebra::DVector<T> },
+            _ => quote! { nalgebra::DMatrix<T> },
+        };
+        let gemm = if gemm(subscripts).is_some() {
+            Some(quote! {
+                + num_traits::Zero
+                + num_traits::One
+                + core::ops::AddAssign
+                + core::ops::MulAssign
+            })
+        } else {
+            None
+        };
+        quote! {
+            fn #fn_name<T, #(#generics),*>(
+                #( #args: &#types ),*
+            ) -> #out_ty
+            where
+                T: nalgebra::Scalar
+                    + core::iter::Sum
+                    + core::ops::Add<Output = T>
+                    + core::ops::Mul<Output = T>
+                    #gemm,
+                #( #predicates ),*
+        }
+    }
+
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let mut appeared: HashSet<char> = HashSet::new();
+        let mut tt = Vec::new();
+        for arg in subscripts.inputs.iter() {
+            if arg.indices().iter().all(|i| appeared.contains(i)) {
+                continue;
+            }
+            let n = tuple(arg.indices().into_iter().map(|i| {
+                if appeared.insert(i) {
+                    let n = n_ident(i);
+                    quote! { #n }
+                } else {
+                    quote! { _ }
+                }
+            }));
+            let shape = shape(arg);
+            tt.push(quote! { let #n = #shape; });
+        }
+        for arg in subscripts
+            .inputs
+            .iter()
+            .filter(|arg| !arg.indices().is_empty())
+        {
+            // local variable, e.g. `n_1`
+            let n_each: Vec<_> = (0..arg.indices().len())
+                .map(|m| format_ident!("n_{}", m))
+                .collect();
+            let n_each_tuple = tuple(n_each.iter().map(|n| quote! { #n }));
+            // size of index defined previously, e.g. `n_i`
+            let n: Vec<_> = arg.indices().into_iter().map(n_ident).collect();
+            let shape = shape(arg);
+            tt.push(quote! {
+                {
+                    let #n_each_tuple = #shape;
+                    #(assert_eq!(#n_each, #n);)*
+                }
+            });
+        }
+        quote! { #(#tt)* }
+    }
+
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let output = &subscripts.output;
+        let n: Vec<syn::Ident> = subscripts
+            .output
+            .indices()
+            .into_iter()
+            .map(n_ident)
+            .collect();
+        let ty = match n.len() {
+            0 => quote! { nalgebra::Matrix1 },
+            1 => quote! { nalgebra::DVector },
+            _ => quote! { nalgebra::DMatrix },
+        };
+        let zero = zero_tt(&quote! { T });
+        quote! {
+            let mut #output = #ty::<T>::from_element(#(#n,)* #zero);
+        }
+    }
+
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], _mutable: bool) -> TokenStream2 {
+        match indices {
+            [] => quote! { #array[0] },
+            [i] => quote! { #array[#i] },
+            _ => quote! { #array[(#(#indices),*)] },
+        }
+    }
+
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2 {
+        if let Some(gemm) = gemm(subscripts) {
+            return gemm;
+        }
+        strided_loops(subscripts, self, true)
+    }
+
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = format_ident!("{}", subscripts.escaped_ident());
+        let args = &subscripts.inputs;
+        let out = &subscripts.output;
+        quote! {
+            let #out = #fn_name(#(&#args),*);
+        }
+    }
+
+    /// 0-rank output is returned as a scalar
+    fn output(&self, output: &Subscript) -> TokenStream2 {
+        if output.indices().is_empty() {
+            quote! { #output[0].clone() }
+        } else {
+            quote! { #output }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{einsum, format_block},
+        *,
+    };
+
+    #[test]
+    fn matmul() {
+        let path = Path::brute_force("ij,jk->ik").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { b }];
+        let tt = format_block(einsum(&Nalgebra, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn ab_bc__ac<T, R0, C0, S0, R1, C1, S1>(
+                arg0: &nalgebra::Matrix<T, R0, C0, S0>,
+                arg1: &nalgebra::Matrix<T, R1, C1, S1>,
+            ) -> nalgebra::DMatrix<T>
+            where
+                T: nalgebra::Scalar
+                    + core::iter::Sum
+                    + core::ops::Add<Output = T>
+                    + core::ops::Mul<Output = T>
+                    + num_traits::Zero
+                    + num_traits::One
+                    + core::ops::AddAssign
+                    + core::ops::MulAssign,
+                R0: nalgebra::Dim,
+                C0: nalgebra::Dim,
+                S0: nalgebra::RawStorage<T, R0, C0>,
+                R1: nalgebra::Dim,
+                C1: nalgebra::Dim,
+                S1: nalgebra::RawStorage<T, R1, C1>,
+            {
+                let (n_a, n_b) = arg0.shape();
+                let (_, n_c) = arg1.shape();
+                {
+                    let (n_0, n_1) = arg0.shape();
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                {
+                    let (n_0, n_1) = arg1.shape();
+                    assert_eq!(n_0, n_b);
+                    assert_eq!(n_1, n_c);
+                }
+                let mut out0 = nalgebra::DMatrix::<T>::from_element(
+                    n_a,
+                    n_c,
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
+                out0.gemm(
+                    <T as num_traits::One>::one(),
+                    &arg0.view((0, 0), arg0.shape()),
+                    &arg1.view((0, 0), arg1.shape()),
+                    <T as num_traits::Zero>::zero(),
+                );
+                out0
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let out0 = ab_bc__ac(&arg0, &arg1);
+            out0
+        }
+        "###);
+    }
+
+    #[test]
+    fn loops() {
+        let path = Path::brute_force("ij,j->i").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { x }];
+        let tt = format_block(einsum(&Nalgebra, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn ab_b__a<T, R0, C0, S0, R1, S1>(
+                arg0: &nalgebra::Matrix<T, R0, C0, S0>,
+                arg1: &nalgebra::Matrix<T, R1, nalgebra::U1, S1>,
+            ) -> nalgebra::DVector<T>
+            where
+                T: nalgebra::Scalar
+                    + core::iter::Sum
+                    + core::ops::Add<Output = T>
+                    + core::ops::Mul<Output = T>,
+                R0: nalgebra::Dim,
+                C0: nalgebra::Dim,
+                S0: nalgebra::RawStorage<T, R0, C0>,
+                R1: nalgebra::Dim,
+                S1: nalgebra::RawStorage<T, R1, nalgebra::U1>,
+            {
+                let (n_a, n_b) = arg0.shape();
+                {
+                    let (n_0, n_1) = arg0.shape();
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                {
+                    let n_0 = arg1.nrows();
+                    assert_eq!(n_0, n_b);
+                }
+                let mut out0 = nalgebra::DVector::<T>::from_element(
+                    n_a,
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
+                for b in 0..n_b {
+                    for a in 0..n_a {
+                        out0[a] = out0[a].clone() + arg0[(a, b)].clone() * arg1[b].clone();
+                    }
+                }
+                out0
+            }
+            let arg0 = a;
+            let arg1 = x;
+            let out0 = ab_b__a(&arg0, &arg1);
+            out0
+        }
+        "###);
+    }
+}
@@ -1,8 +1,13 @@
 //! For [ndarray](https://crates.io/crates/ndarray) crate
 
 pub mod naive;
+pub mod named;
 
-use crate::subscripts::Subscripts;
+use super::{print::tuple, EinsumBackend};
+use crate::{
+    subscripts::{Subscript, Subscripts},
+    Path, Position,
+};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{format_ident, quote};
 
@@ -11,13 +16,112 @@ fn dim(n: usize) -> syn::Path {
     syn::parse_quote! { ndarray::#ix }
 }
 
+/// Position of the operand in messages, e.g. `1st`
+fn ordinal(position: &Position) -> String {
+    let n = match position {
+        Position::Arg(n) | Position::Out(n) => n + 1,
+    };
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Identifiers of the storage types of each input, e.g. `S0`
+fn storages(n: usize) -> Vec<syn::Ident> {
+    (0..n).map(|n| format_ident!("S{}", n)).collect()
+}
+
+/// How the element type `T` of the output array is determined from the inputs
+#[derive(Clone)]
+pub enum ElemType {
+    /// The output type of multiplying the input elements,
+    /// e.g. `T = <T0 as Mul<T1>>::Output` for two inputs.
+    Product,
+    /// Given explicitly, e.g. `einsum!("ij,jk->ik", a, b -> f64)`.
+    /// Each element of inputs is converted into `T` using `From`.
+    Explicit(Box<syn::Type>),
+}
+
+impl ElemType {
+    /// Identifiers of the element types of each input, e.g. `T0`
+    fn input_elems(&self, n: usize) -> Vec<syn::Ident> {
+        match self {
+            // No multiplication, the input element is the output element
+            ElemType::Product if n == 1 => vec![format_ident!("T")],
+            _ => (0..n).map(|n| format_ident!("T{}", n)).collect(),
+        }
+    }
+
+    fn elem_generics(&self, n: usize) -> Vec<syn::Ident> {
+        match self {
+            ElemType::Product if n == 1 => Vec::new(),
+            _ => self.input_elems(n),
+        }
+    }
+
+    /// Bounds for combining input elements into `T`
+    fn elem_predicates(&self, n: usize) -> Vec<TokenStream2> {
+        let elems = self.input_elems(n);
+        match self {
+            ElemType::Product if n == 1 => Vec::new(),
+            ElemType::Product => {
+                let mut tt = Vec::new();
+                let (t0, t1) = (&elems[0], &elems[1]);
+                tt.push(quote! { #t0: Clone + core::ops::Mul<#t1, Output = T> });
+                for t in &elems[1..] {
+                    tt.push(quote! { #t: Clone });
+                }
+                for t in &elems[2..] {
+                    tt.push(quote! { T: core::ops::Mul<#t, Output = T> });
+                }
+                tt
+            }
+            ElemType::Explicit(_) => {
+                let mut tt: Vec<_> = elems.iter().map(|t| quote! { #t: Clone }).collect();
+                let mul = if n >= 2 {
+                    Some(quote! { + core::ops::Mul<Output = T> })
+                } else {
+                    None
+                };
+                tt.push(quote! { T: #(From<#elems>)+* #mul });
+                tt
+            }
+        }
+    }
+}
+
 /// Generate einsum function definition
-pub fn function_definition(subscripts: &Subscripts, inner: TokenStream2) -> TokenStream2 {
+///
+/// `bounds` are the `where` predicates required by the kernel generating `inner`,
+/// e.g. [naive::bounds]. Kernels calling BLAS routines will require
+/// `T: ndarray::LinalgScalar` instead.
+/// The bounds for combining input elements into `T` are determined by `elem_type`.
+pub fn function_definition(
+    subscripts: &Subscripts,
+    elem_type: &ElemType,
+    bounds: TokenStream2,
+    inner: TokenStream2,
+) -> TokenStream2 {
+    let signature = signature(subscripts, elem_type, bounds);
+    quote! {
+        #signature {
+            #inner
+        }
+    }
+}
+
+/// Signature of [function_definition]
+fn signature(subscripts: &Subscripts, elem_type: &ElemType, bounds: TokenStream2) -> TokenStream2 {
     let fn_name = format_ident!("{}", subscripts.escaped_ident());
     let n = subscripts.inputs.len();
 
     let args = &subscripts.inputs;
-    let storages: Vec<syn::Ident> = (0..n).map(|n| quote::format_ident!("S{}", n)).collect();
+    let storages = storages(n);
     let dims: Vec<syn::Path> = subscripts
         .inputs
         .iter()
@@ -26,41 +130,267 @@ pub fn function_definition(subscripts: &Subscripts, inner: TokenStream2) -> Toke
 
     let out_dim = dim(subscripts.output.indices().len());
 
+    let elem_generics = elem_type.elem_generics(n);
+    let elems = elem_type.input_elems(n);
+    let elem_predicates = elem_type.elem_predicates(n);
+
     quote! {
-        fn #fn_name<T, #(#storages),*>(
+        fn #fn_name<T, #(#elem_generics,)* #(#storages),*>(
             #( #args: ndarray::ArrayBase<#storages, #dims> ),*
         ) -> ndarray::Array<T, #out_dim>
         where
-            T: ndarray::LinalgScalar,
-            #( #storages: ndarray::Data<Elem = T> ),*
-        {
-            #inner
+            #bounds,
+            #( #elem_predicates, )*
+            #( #storages: ndarray::Data<Elem = #elems> ),*
+    }
+}
+
+/// Generate a call of the function defined by [function_definition]
+///
+/// The output element type is specified explicitly for [ElemType::Explicit] case
+/// since it cannot be inferred from the inputs.
+pub fn function_call(subscripts: &Subscripts, elem_type: &ElemType) -> TokenStream2 {
+    match elem_type {
+        ElemType::Product => quote! { #subscripts },
+        ElemType::Explicit(ty) => {
+            let fn_name = format_ident!("{}", subscripts.escaped_ident());
+            let args = &subscripts.inputs;
+            let out = &subscripts.output;
+            let placeholders = (0..2 * args.len()).map(|_| quote! { _ });
+            quote! {
+                let #out = #fn_name::<#ty, #(#placeholders),*>(#(#args),*);
+            }
+        }
+    }
+}
+
+/// Element of the array by `Index` and `IndexMut`, or by `uget` and `uget_mut` if `unchecked`
+///
+/// The mutable element is a place expression, e.g. `(*out0.uget_mut((a, c)))`.
+fn index(
+    array: &TokenStream2,
+    indices: &[syn::Ident],
+    mutable: bool,
+    unchecked: bool,
+) -> TokenStream2 {
+    let index = tuple(indices.iter().map(|i| quote! { #i }));
+    match (unchecked, mutable) {
+        (false, _) => quote! { #array[#index] },
+        (true, false) => quote! { #array.uget(#index) },
+        (true, true) => quote! { (*#array.uget_mut(#index)) },
+    }
+}
+
+/// [EinsumBackend] for ndarray generating the loops of [naive]
+#[derive(Clone)]
+pub struct Ndarray {
+    pub elem_type: ElemType,
+    pub options: naive::Options,
+}
+
+impl EinsumBackend for Ndarray {
+    /// The partial sums of [naive::Options::parallel] are collected into `alloc::vec::Vec`
+    fn uses_alloc(&self, _path: &Path) -> bool {
+        self.options.parallel.is_some()
+    }
+
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let bounds = naive::bounds(subscripts, &self.elem_type, &self.options);
+        signature(subscripts, &self.elem_type, bounds)
+    }
+
+    fn helpers(&self, _subscripts: &Subscripts) -> TokenStream2 {
+        naive::helpers(&self.options)
+    }
+
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let array_size = naive::define_array_size(subscripts);
+        let array_size_asserts = naive::array_size_asserts(subscripts);
+        quote! {
+            #array_size
+            #array_size_asserts
+        }
+    }
+
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2 {
+        naive::define_output_array(subscripts)
+    }
+
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], mutable: bool) -> TokenStream2 {
+        index(array, indices, mutable, self.options.unchecked)
+    }
+
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2 {
+        naive::contraction(subscripts, &self.elem_type, &self.options)
+    }
+
+    /// Convert the input into the rank of the subscript for [naive::Options::from_dyn],
+    /// e.g. from `ndarray::ArrayD`, which panics if the rank does not match
+    fn argument(&self, subscript: &Subscript, arg: &syn::Expr) -> TokenStream2 {
+        if !self.options.from_dyn {
+            return quote! { let #subscript = #arg; };
+        }
+        let rank = subscript.indices().len();
+        let dim = dim(rank);
+        let message = format!(
+            "einsum: {} operand has {{}} axes, but its subscript requires {}",
+            ordinal(subscript.position()),
+            rank
+        );
+        quote! {
+            let #subscript = {
+                let arg = #arg;
+                let ndim = arg.ndim();
+                match arg.into_dimensionality::<#dim>() {
+                    Ok(arg) => arg,
+                    Err(_) => panic!(#message, ndim),
+                }
+            };
+        }
+    }
+
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        function_call(subscripts, &self.elem_type)
+    }
+
+    /// Fully contracted subscripts, e.g. `i,i->`, return a scalar instead of a 0-rank array,
+    /// and `ndarray::ArrayD` is returned for [naive::Options::into_dyn]
+    fn output(&self, output: &Subscript) -> TokenStream2 {
+        if self.options.into_dyn {
+            quote! { #output.into_dyn() }
+        } else if output.indices().is_empty() {
+            quote! { #output.into_scalar() }
+        } else {
+            quote! { #output }
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{codegen::format_block, *};
+    use crate::{
+        codegen::{format_block, EinsumBackend},
+        *,
+    };
 
     #[test]
     fn function_definition_snapshot() {
         let mut namespace = Namespace::init();
         let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
         let inner = quote::quote! { todo!() };
-        let tt = format_block(super::function_definition(&subscripts, inner).to_string());
+        let tt = format_block(
+            super::function_definition(
+                &subscripts,
+                &super::ElemType::Product,
+                super::naive::bounds(&subscripts, &super::ElemType::Product, &Default::default()),
+                inner,
+            )
+            .to_string(),
+        );
         insta::assert_snapshot!(tt, @r###"
-        fn ab_bc__ac<T, S0, S1>(
+        fn ab_bc__ac<T, T0, T1, S0, S1>(
             arg0: ndarray::ArrayBase<S0, ndarray::Ix2>,
             arg1: ndarray::ArrayBase<S1, ndarray::Ix2>,
         ) -> ndarray::Array<T, ndarray::Ix2>
         where
-            T: ndarray::LinalgScalar,
-            S0: ndarray::Data<Elem = T>,
-            S1: ndarray::Data<Elem = T>,
+            T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+            T0: Clone + core::ops::Mul<T1, Output = T>,
+            T1: Clone,
+            S0: ndarray::Data<Elem = T0>,
+            S1: ndarray::Data<Elem = T1>,
         {
             todo!()
         }
         "###);
     }
+
+    #[test]
+    fn argument_from_dyn() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,j->i").unwrap();
+        let arg: syn::Expr = syn::parse_quote! { a };
+        let mut backend = super::Ndarray {
+            elem_type: super::ElemType::Product,
+            options: Default::default(),
+        };
+        let tt = backend.argument(&subscripts.inputs[1], &arg);
+        insta::assert_snapshot!(tt.to_string(), @"let arg1 = a ;");
+
+        backend.options.from_dyn = true;
+        let tt = format_block(backend.argument(&subscripts.inputs[1], &arg).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        let arg1 = {
+            let arg = a;
+            let ndim = arg.ndim();
+            match arg.into_dimensionality::<ndarray::Ix1>() {
+                Ok(arg) => arg,
+                Err(_) => panic!(
+                    "einsum: 2nd operand has {} axes, but its subscript requires 1",
+                    ndim
+                ),
+            }
+        };
+        "###);
+    }
+
+    #[test]
+    fn function_definition_explicit() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let inner = quote::quote! { todo!() };
+        let elem_type = super::ElemType::Explicit(Box::new(syn::parse_quote! { f64 }));
+        let tt = format_block(
+            super::function_definition(
+                &subscripts,
+                &elem_type,
+                super::naive::bounds(&subscripts, &elem_type, &Default::default()),
+                inner,
+            )
+            .to_string(),
+        );
+        insta::assert_snapshot!(tt, @r###"
+        fn ab_bc__ac<T, T0, T1, S0, S1>(
+            arg0: ndarray::ArrayBase<S0, ndarray::Ix2>,
+            arg1: ndarray::ArrayBase<S1, ndarray::Ix2>,
+        ) -> ndarray::Array<T, ndarray::Ix2>
+        where
+            T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+            T0: Clone,
+            T1: Clone,
+            T: From<T0> + From<T1> + core::ops::Mul<Output = T>,
+            S0: ndarray::Data<Elem = T0>,
+            S1: ndarray::Data<Elem = T1>,
+        {
+            todo!()
+        }
+        "###);
+
+        let tt = format_block(super::function_call(&subscripts, &elem_type).to_string());
+        insta::assert_snapshot!(tt, @"let out0 = ab_bc__ac::<f64, _, _, _, _>(arg0, arg1);");
+    }
+
+    #[test]
+    fn backend() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let backend = super::Ndarray {
+            elem_type: super::ElemType::Product,
+            options: Default::default(),
+        };
+        // The default composition agrees with the naive function body
+        let expected = super::function_definition(
+            &subscripts,
+            &backend.elem_type,
+            super::naive::bounds(&subscripts, &backend.elem_type, &backend.options),
+            super::naive::inner(&subscripts, &backend.elem_type, &backend.options),
+        );
+        assert_eq!(
+            backend.function_definition(&subscripts).to_string(),
+            expected.to_string()
+        );
+
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "i,i->").unwrap();
+        let tt = backend.output(&subscripts.output);
+        insta::assert_snapshot!(tt, @"out1 . into_scalar ()");
+    }
 }
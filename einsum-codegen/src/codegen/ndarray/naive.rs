@@ -3,62 +3,147 @@
 #[cfg(doc)]
 use super::function_definition;
 
-use crate::Subscripts;
+use super::{ElemType, Ndarray};
+use crate::{
+    codegen::print::{n_ident, product_of, tuple, zero_tt, Printer},
+    ir::Kernel,
+    Position, Subscripts,
+};
 
+use anyhow::{bail, Error, Result};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use std::collections::HashSet;
+use std::{collections::HashSet, str::FromStr};
 
-fn index_ident(i: char) -> syn::Ident {
-    quote::format_ident!("{}", i)
+/// Options for generating naive contraction loop
+#[derive(Clone, Default)]
+pub struct Options {
+    /// Accumulate the products in this type, e.g. `f64` for `f32` inputs
+    ///
+    /// The elements of inputs are converted into this type using `From`,
+    /// and the sum is converted into the output element type `T`
+    /// using `num_traits::AsPrimitive` once per output element,
+    /// so the user crate has to depend on `num-traits` to use this option.
+    ///
+    /// The conversion is the `as` cast without checks, so a sum out of the range of `T`
+    /// is truncated for integers, e.g. `300_i32` into `44_i8`, saturated from floats into integers,
+    /// and becomes infinity from `f64` into `f32`.
+    pub accumulate: Option<syn::Type>,
+    /// Summation algorithm along the contraction indices
+    pub summation: Summation,
+    /// Sum up in a fixed order independent of how the loops are executed
+    ///
+    /// The contraction indices are flattened and split into chunks of [DETERMINISTIC_CHUNK] terms.
+    /// Each chunk is summed up by [Options::summation],
+    /// and the partial sums are combined in a fixed binary tree order.
+    pub deterministic: bool,
+    /// Run the outermost output loop in parallel using rayon
+    /// if the number of terms, i.e. the product of all index sizes, is at least this threshold
+    ///
+    /// The generated code uses `ndarray::parallel`, which requires `rayon` feature of ndarray.
+    /// For 0-rank output, the chunks of [Options::deterministic] summation run in parallel instead.
+    /// Each output element is summed up in the same order as sequential execution.
+    pub parallel: Option<usize>,
+    /// Split the loops into tiles to reuse the elements in cache
+    ///
+    /// The output indices and the outermost contraction index are tiled,
+    /// so that each output element is summed up in the same order as untiled loops.
+    pub tile: Option<Tile>,
+    /// Index the arrays by `uget` and `uget_mut` without bounds checks
+    ///
+    /// The loops are placed in an `unsafe` block after [array_size_asserts]
+    /// checking that all the indices are in bounds,
+    /// and the safety argument is emitted in the doc comments before the block.
+    pub unchecked: bool,
+    /// Return the output as `ndarray::ArrayD`, including 0-rank output instead of a scalar
+    pub into_dyn: bool,
+    /// Accept the inputs of the rank known only at runtime, e.g. `ndarray::ArrayD`,
+    /// by converting them into the rank of the subscripts, which panics on mismatch
+    ///
+    /// Without this option, the ranks of inputs are checked at compile time.
+    pub from_dyn: bool,
+    /// Compute the steps by einsum-runtime crate instead of generating the loops, see [crate::codegen::runtime]
+    pub runtime: bool,
+    /// Generate a loop nest for each combination of row-major and column-major layouts of user inputs,
+    /// and dispatch on their `strides()` at runtime
+    ///
+    /// Without this option, a single loop nest assuming row-major inputs is generated.
+    /// This option multiplies the generated code up to four times.
+    pub layout_dispatch: bool,
 }
 
-fn n_ident(i: char) -> syn::Ident {
-    quote::format_ident!("n_{}", i)
+/// Tile size of [Options::tile]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    /// Determined from the number of tiled indices in each array, see [heuristic_tile]
+    Auto,
+    /// Given explicitly
+    Fixed(usize),
 }
 
-fn contraction_for(indices: &[char], inner: TokenStream2) -> TokenStream2 {
-    let mut tt = inner;
-    for &i in indices.iter().rev() {
-        let index = index_ident(i);
-        let n = n_ident(i);
-        tt = quote! {
-            for #index in 0..#n { #tt }
-        };
+/// Cache size which the tiles of all arrays should fit in, assuming L1 data cache
+const TILE_CACHE_SIZE: usize = 32 * 1024;
+
+/// Element size assumed in [heuristic_tile] since the element type is generic
+const TILE_ELEM_SIZE: usize = 8;
+
+/// Largest power of two tile size whose tiles of the inputs and output fit in [TILE_CACHE_SIZE]
+///
+/// Tiles of an array with `d` tiled indices have `tile^d` elements.
+pub fn heuristic_tile(subscripts: &Subscripts, tiled: &[char]) -> usize {
+    let dims: Vec<u32> = subscripts
+        .inputs
+        .iter()
+        .chain(std::iter::once(&subscripts.output))
+        .map(|ss| ss.indices().iter().filter(|i| tiled.contains(i)).count() as u32)
+        .collect();
+    let mut tile: usize = 256;
+    while tile > 4
+        && dims.iter().map(|&d| tile.pow(d)).sum::<usize>() * TILE_ELEM_SIZE > TILE_CACHE_SIZE
+    {
+        tile /= 2;
     }
-    tt
+    tile
 }
 
-fn contraction_inner(subscripts: &Subscripts) -> TokenStream2 {
-    let mut inner_args_tt = Vec::new();
-    for (argc, arg) in subscripts.inputs.iter().enumerate() {
-        let mut index = Vec::new();
-        for i in subscripts.inputs[argc].indices() {
-            index.push(index_ident(i));
-        }
-        inner_args_tt.push(quote! {
-            #arg[(#(#index),*)]
-        })
-    }
-    let mut inner_mul = None;
-    for inner in inner_args_tt {
-        match inner_mul {
-            Some(i) => inner_mul = Some(quote! { #i * #inner }),
-            None => inner_mul = Some(inner),
-        }
-    }
+/// Default threshold of [Options::parallel]
+pub const PARALLEL_THRESHOLD: usize = 1 << 16;
 
-    let output_ident = &subscripts.output;
-    let mut output_indices = Vec::new();
-    for i in &subscripts.output.indices() {
-        let index = index_ident(*i);
-        output_indices.push(index.clone());
-    }
-    quote! {
-        #output_ident[(#(#output_indices),*)] = #inner_mul;
+/// Number of terms in a chunk of [Options::deterministic] summation
+///
+/// This is a part of the result, i.e. changing this value changes the rounding errors.
+pub const DETERMINISTIC_CHUNK: usize = 1024;
+
+/// Summation algorithm along the contraction indices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Summation {
+    /// Add the products one by one in the loop order.
+    /// The error bound grows linearly in the number of terms.
+    #[default]
+    Sequential,
+    /// Kahan's compensated summation.
+    /// The error bound does not depend on the number of terms.
+    Kahan,
+    /// Pairwise (cascade) summation.
+    /// The error bound grows logarithmically in the number of terms.
+    Pairwise,
+}
+
+impl FromStr for Summation {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "sequential" => Ok(Summation::Sequential),
+            "kahan" => Ok(Summation::Kahan),
+            "pairwise" => Ok(Summation::Pairwise),
+            _ => bail!("Unknown summation algorithm: {}", input),
+        }
     }
 }
 
+/// Number of terms summed up sequentially at the leaves of pairwise summation
+const PAIRWISE_BLOCK: usize = 8;
+
 /// Generate naive contraction loop
 ///
 /// ```
@@ -70,22 +155,489 @@ fn contraction_inner(subscripts: &Subscripts) -> TokenStream2 {
 /// # let n_j = 3;
 /// # let n_k = 3;
 /// for i in 0..n_i {
+///     for j in 0..n_j {
+///         let prod0 = arg0[(i, j)].clone();
+///         for k in 0..n_k {
+///             out0[(i, k)] = out0[(i, k)].clone() + prod0.clone() * arg1[(j, k)].clone();
+///         }
+///     }
+/// }
+/// ```
+///
+/// The partial products of the elements invariant in the inner loops are hoisted out of them.
+/// If the contraction loops are innermost, e.g. for column-major `arg1`,
+/// the products are summed up into a local variable written once per output element:
+///
+/// ```
+/// # use ndarray::Array2;
+/// # let arg0 = Array2::<f64>::zeros((3, 3));
+/// # let arg1 = Array2::<f64>::zeros((3, 3));
+/// # let mut out0 = Array2::<f64>::zeros((3, 3));
+/// # let n_i = 3;
+/// # let n_j = 3;
+/// # let n_k = 3;
+/// for i in 0..n_i {
 ///     for k in 0..n_k {
+///         let mut sum = out0[(i, k)].clone();
 ///         for j in 0..n_j {
-///             out0[(i, k)] = arg0[(i, j)] * arg1[(j, k)];
+///             sum = sum + arg0[(i, j)].clone() * arg1[(j, k)].clone();
 ///         }
+///         out0[(i, k)] = sum;
 ///     }
 /// }
 /// ```
 ///
-pub fn contraction(subscripts: &Subscripts) -> TokenStream2 {
-    let mut indices: Vec<char> = subscripts.output.indices();
-    for i in subscripts.contraction_indices() {
-        indices.push(i);
+/// The loop order is chosen so that the index with the smallest strides runs fastest.
+/// The output and intermediate arrays are row-major since the generated code creates them,
+/// and the user inputs are assumed to be row-major.
+/// With [Options::layout_dispatch], the layouts of the user inputs are checked at runtime by their `strides()`,
+/// and the loop nest is dispatched to the one precomputed for each combination of
+/// row-major and column-major layouts.
+/// Since the function definition is shared among the steps with the same subscripts,
+/// the layouts assumed for intermediates are only a performance hint, i.e. any loop order gives correct results.
+///
+/// With [Options::accumulate], the products are summed up into a local variable
+/// in the loops of contraction indices:
+///
+/// ```
+/// # use ndarray::Array2;
+/// # let arg0 = Array2::<f32>::zeros((3, 3));
+/// # let arg1 = Array2::<f32>::zeros((3, 3));
+/// # let mut out0 = Array2::<f32>::zeros((3, 3));
+/// # let n_i = 3;
+/// # let n_j = 3;
+/// # let n_k = 3;
+/// # type T = f32;
+/// for i in 0..n_i {
+///     for k in 0..n_k {
+///         let mut sum = <f64 as core::iter::Sum>::sum(core::iter::empty());
+///         for j in 0..n_j {
+///             sum += <f64>::from(arg0[(i, j)].clone()) * <f64>::from(arg1[(j, k)].clone());
+///         }
+///         out0[(i, k)] = num_traits::AsPrimitive::<T>::as_(sum);
+///     }
+/// }
+/// ```
+///
+pub fn contraction(
+    subscripts: &Subscripts,
+    elem_type: &ElemType,
+    options: &Options,
+) -> TokenStream2 {
+    let tt = layout_dispatch(subscripts, elem_type, options);
+    if !options.unchecked {
+        return tt;
+    }
+    let safety = safety_argument(subscripts, options);
+    quote! {
+        #[allow(unused_doc_comments)]
+        #(#[doc = #safety])*
+        unsafe { #tt }
+    }
+}
+
+/// Safety argument of the `unsafe` block of [contraction] for [Options::unchecked]
+///
+/// The block is placed after [array_size_asserts], and the output array is created
+/// with the checked sizes by [define_output_array]. The argument refers to the names of these asserts
+/// and sizes in the generated code, and is attached to the block as doc comments,
+/// which are printed as `// SAFETY:` comments by [crate::build].
+fn safety_argument(subscripts: &Subscripts, options: &Options) -> Vec<String> {
+    let shape = |indices: &[char]| {
+        let n: Vec<String> = indices.iter().map(|i| n_ident(*i).to_string()).collect();
+        match n.as_slice() {
+            [n] => n.clone(),
+            _ => format!("({})", n.join(", ")),
+        }
+    };
+    let mut lines = vec![
+        " SAFETY: All the indices passed to `uget` and `uget_mut` in this block are in bounds:"
+            .to_string(),
+    ];
+    for arg in &subscripts.inputs {
+        let indices = arg.indices();
+        if indices.is_empty() {
+            continue;
+        }
+        let asserts: Vec<String> = indices
+            .iter()
+            .enumerate()
+            .map(|(m, i)| format!("`assert_eq!(n_{}, {})`", m, n_ident(*i)))
+            .collect();
+        lines.push(format!(
+            " - `{}.dim()` is `{}` by {} above",
+            arg.position(),
+            shape(&indices),
+            asserts.join(", ")
+        ));
+    }
+    let output = subscripts.output.indices();
+    if !output.is_empty() {
+        lines.push(format!(
+            " - `{}` is created with the shape `{}`",
+            subscripts.output.position(),
+            shape(&output)
+        ));
+    }
+    let mut indices: Vec<char> = subscripts
+        .inputs
+        .iter()
+        .flat_map(|arg| arg.indices())
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    let ranges: Vec<String> = indices
+        .iter()
+        .map(|i| format!("`{}` in `0..{}`", i, n_ident(*i)))
+        .collect();
+    lines.push(format!(
+        " - The loops, tiles and flattened indices decoded by `l / stride % n` run {}",
+        ranges.join(", ")
+    ));
+    if options.parallel.is_some() {
+        lines.push(format!(
+            " - The parallel loops index the subview of `{}` by the remaining indices",
+            subscripts.output.position()
+        ));
+    }
+    lines
+}
+
+/// Dispatch the loop nests of [contraction] on the layouts of user inputs with [Options::layout_dispatch]
+fn layout_dispatch(
+    subscripts: &Subscripts,
+    elem_type: &ElemType,
+    options: &Options,
+) -> TokenStream2 {
+    // Intermediate arrays and the output array are created in C layout by the generated code,
+    // while the layouts of user inputs are known only at runtime.
+    let runtime: Vec<usize> = subscripts
+        .inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, arg)| matches!(arg.position(), Position::Arg(_)) && arg.indices().len() >= 2)
+        .map(|(n, _)| n)
+        .collect();
+    let combinations: Vec<Vec<Layout>> = if !options.layout_dispatch {
+        vec![vec![Layout::C; runtime.len()]]
+    } else if runtime.len() <= 2 {
+        (0..1 << runtime.len())
+            .map(|bits: usize| {
+                (0..runtime.len())
+                    .map(|k| {
+                        if bits >> k & 1 == 1 {
+                            Layout::F
+                        } else {
+                            Layout::C
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    } else {
+        // Avoid exponential number of loop nests
+        vec![
+            vec![Layout::C; runtime.len()],
+            vec![Layout::F; runtime.len()],
+        ]
+    };
+
+    let layouts_of = |combination: &[Layout]| {
+        let mut layouts = vec![Layout::C; subscripts.inputs.len()];
+        for (&n, &layout) in runtime.iter().zip(combination) {
+            layouts[n] = layout;
+        }
+        layouts
+    };
+    let default = contraction_with_layouts(
+        subscripts,
+        elem_type,
+        options,
+        &layouts_of(&combinations[0]),
+    );
+
+    // Group the combinations of layouts by the generated loop nest
+    let mut branches: Vec<(Vec<TokenStream2>, TokenStream2)> = Vec::new();
+    for combination in &combinations[1..] {
+        let tt = contraction_with_layouts(subscripts, elem_type, options, &layouts_of(combination));
+        if tt.to_string() == default.to_string() {
+            continue;
+        }
+        let pattern = combination.iter().map(|layout| match layout {
+            Layout::C => quote! { false },
+            Layout::F => quote! { true },
+        });
+        let pattern = tuple(pattern);
+        match branches
+            .iter_mut()
+            .find(|(_, branch)| branch.to_string() == tt.to_string())
+        {
+            Some((patterns, _)) => patterns.push(pattern),
+            None => branches.push((vec![pattern], tt)),
+        }
+    }
+    if branches.is_empty() {
+        return default;
+    }
+
+    // Whether each user input is closer to column-major layout
+    let f_layouts = runtime.iter().map(|&n| {
+        let arg = &subscripts.inputs[n];
+        let last = proc_macro2::Literal::usize_unsuffixed(arg.indices().len() - 1);
+        quote! { #arg.strides()[0].unsigned_abs() < #arg.strides()[#last].unsigned_abs() }
+    });
+    let f_layouts = tuple(f_layouts);
+    let branches = branches.into_iter().map(|(patterns, tt)| {
+        quote! {
+            #(#patterns)|* => { #tt }
+        }
+    });
+    quote! {
+        match #f_layouts {
+            #(#branches)*
+            _ => { #default }
+        }
+    }
+}
+
+/// Memory layout of an array assumed in choosing the loop order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// Row-major, the last index runs fastest
+    C,
+    /// Column-major, the first index runs fastest
+    F,
+}
+
+/// Rank of the stride of index `i` in the array, 0 for the smallest stride.
+/// The index not appearing in the array is regarded as stride zero.
+fn stride_rank(indices: &[char], layout: Layout, i: char) -> usize {
+    match indices.iter().position(|&j| j == i) {
+        Some(p) => match layout {
+            Layout::C => indices.len() - 1 - p,
+            Layout::F => p,
+        },
+        None => 0,
+    }
+}
+
+/// Sort `indices` so that the indices with larger strides in the inputs and output come outer
+///
+/// The sort is stable, i.e. the given order is kept for indices with the same score.
+fn loop_order(subscripts: &Subscripts, layouts: &[Layout], indices: &[char]) -> Vec<char> {
+    let output = subscripts.output.indices();
+    let score = |i: char| -> usize {
+        subscripts
+            .inputs
+            .iter()
+            .zip(layouts)
+            .map(|(arg, &layout)| stride_rank(&arg.indices(), layout, i))
+            .sum::<usize>()
+            + stride_rank(&output, Layout::C, i)
+    };
+    let mut indices = indices.to_vec();
+    indices.sort_by_key(|&i| std::cmp::Reverse(score(i)));
+    indices
+}
+
+/// Loops of [contraction] assuming the layouts of inputs
+fn contraction_with_layouts(
+    subscripts: &Subscripts,
+    elem_type: &ElemType,
+    options: &Options,
+    layouts: &[Layout],
+) -> TokenStream2 {
+    let output_indices = subscripts.output.indices();
+    let threshold = match options.parallel {
+        Some(threshold) => threshold,
+        None => return contraction_loops(subscripts, elem_type, options, layouts, false),
+    };
+    if output_indices.is_empty() {
+        // No output index to be split, only chunks of deterministic summation run in parallel
+        return contraction_loops(subscripts, elem_type, options, layouts, true);
+    }
+
+    let mut sizes: Vec<syn::Ident> = output_indices.iter().cloned().map(n_ident).collect();
+    sizes.extend(subscripts.contraction_indices().into_iter().map(n_ident));
+    let work = product_of(&sizes);
+    let sequential = contraction_loops(subscripts, elem_type, options, layouts, false);
+    let parallel = contraction_loops(subscripts, elem_type, options, layouts, true);
+    quote! {
+        if #work >= #threshold {
+            #parallel
+        } else {
+            #sequential
+        }
+    }
+}
+
+/// Loops over output indices and contraction indices
+///
+/// The subscripts are lowered into [Kernel], transformed by its passes, and printed.
+/// The loop order is chosen by [loop_order] for the `layouts` of inputs.
+/// If `parallel` is true, the loop of the first output index runs in parallel,
+/// or the chunks of [Options::deterministic] summation for 0-rank output.
+fn contraction_loops(
+    subscripts: &Subscripts,
+    elem_type: &ElemType,
+    options: &Options,
+    layouts: &[Layout],
+    parallel: bool,
+) -> TokenStream2 {
+    let contraction_indices: Vec<char> = subscripts.contraction_indices().into_iter().collect();
+    let mut kernel = Kernel::lower(subscripts);
+    let mut output_indices = kernel.output.clone();
+
+    // Summation algorithm is meaningless for a single term
+    let local_sum = options.accumulate.is_some()
+        || ((options.summation != Summation::Sequential || options.deterministic)
+            && !contraction_indices.is_empty());
+    // The order of terms is a part of the result for these cases
+    let reduce = local_sum && (options.deterministic || options.summation == Summation::Pairwise);
+    if reduce {
+        kernel.reduce();
+    }
+    if parallel {
+        kernel.parallel();
+        if !output_indices.is_empty() {
+            output_indices.remove(0);
+        }
     }
+    let tile = |kernel: &mut Kernel, tiled: &[char]| match options.tile {
+        Some(Tile::Fixed(tile)) => kernel.tile(tiled, tile),
+        Some(Tile::Auto) => kernel.tile(tiled, heuristic_tile(subscripts, tiled)),
+        None => {}
+    };
 
-    let inner = contraction_inner(subscripts);
-    contraction_for(&indices, inner)
+    if local_sum {
+        // The contraction loops are inside, and only output indices are tiled
+        let mut order = loop_order(subscripts, layouts, &output_indices);
+        let tiled = order.clone();
+        if !reduce {
+            order.extend(loop_order(subscripts, layouts, &contraction_indices));
+        }
+        kernel.interchange(&order);
+        tile(&mut kernel, &tiled);
+        if !reduce {
+            kernel.hoist();
+            kernel.accumulate_locally(false);
+        }
+    } else {
+        let mut indices = output_indices.clone();
+        indices.extend(contraction_indices.iter().cloned());
+        let order = loop_order(subscripts, layouts, &indices);
+        kernel.interchange(&order);
+        // Tiling the other contraction indices changes the order of terms
+        let mut tiled = output_indices.clone();
+        tiled.extend(order.iter().find(|i| contraction_indices.contains(i)));
+        tile(&mut kernel, &tiled);
+        kernel.hoist();
+        // Sum up in a local variable written once per output element
+        // if the contraction loops are innermost
+        if !contraction_indices.is_empty() {
+            kernel.accumulate_locally(true);
+        }
+    }
+    let backend = Ndarray {
+        elem_type: elem_type.clone(),
+        options: options.clone(),
+    };
+    Printer::new(subscripts, elem_type, options, &backend)
+        .with_lanes()
+        .kernel(&kernel)
+}
+
+/// Helper function used in the loop generated by [contraction] with [Summation::Pairwise]
+fn pairwise_sum_definition() -> TokenStream2 {
+    let block = PAIRWISE_BLOCK;
+    let zero = zero_tt(&quote! { L });
+    quote! {
+        fn pairwise_sum<L, F>(begin: usize, end: usize, term: &F) -> L
+        where
+            L: core::iter::Sum + core::ops::Add<Output = L>,
+            F: Fn(usize) -> L,
+        {
+            if end - begin <= #block {
+                let mut sum = #zero;
+                for l in begin..end {
+                    sum = sum + term(l);
+                }
+                sum
+            } else {
+                let middle = begin + (end - begin) / 2;
+                pairwise_sum(begin, middle, term) + pairwise_sum(middle, end, term)
+            }
+        }
+    }
+}
+
+/// Helper function used in the loop generated by [contraction] with [Options::deterministic]
+fn tree_sum_definition() -> TokenStream2 {
+    let zero = zero_tt(&quote! { L });
+    quote! {
+        fn tree_sum<L, F>(begin: usize, end: usize, chunk_sum: &F) -> L
+        where
+            L: core::iter::Sum + core::ops::Add<Output = L>,
+            F: Fn(usize) -> L,
+        {
+            match end - begin {
+                0 => #zero,
+                1 => chunk_sum(begin),
+                _ => {
+                    let middle = begin + (end - begin) / 2;
+                    tree_sum(begin, middle, chunk_sum) + tree_sum(middle, end, chunk_sum)
+                }
+            }
+        }
+    }
+}
+
+/// Trait bounds required by [contraction] as `where` predicates
+///
+/// The naive loop only clones elements, and sums up their products starting from zero,
+/// so it works for integers and custom number types which do not satisfy `ndarray::LinalgScalar`.
+/// The zero is `T::sum(core::iter::empty())`, so `core::iter::Sum` of `T` must return
+/// the additive identity for an empty iterator.
+/// The bounds for multiplication in `T` are given by [function_definition].
+pub fn bounds(subscripts: &Subscripts, elem_type: &ElemType, options: &Options) -> TokenStream2 {
+    let local = match &options.accumulate {
+        Some(acc) => quote! { #acc },
+        None => quote! { T },
+    };
+    let sub = match options.summation {
+        Summation::Kahan => Some(quote! { + core::ops::Sub<Output = #local> }),
+        _ => None,
+    };
+    let mut tt = match &options.accumulate {
+        Some(acc) => {
+            let elems = elem_type.input_elems(subscripts.inputs.len());
+            quote! {
+                T: Clone + core::iter::Sum + Copy + 'static,
+                #acc: Clone
+                    + core::iter::Sum
+                    + core::ops::Add<Output = #acc>
+                    + core::ops::AddAssign
+                    + core::ops::Mul<Output = #acc>
+                    + num_traits::AsPrimitive<T>
+                    #(+ From<#elems>)*
+                    #sub
+            }
+        }
+        None => quote! {
+            T: Clone + core::iter::Sum + core::ops::Add<Output = T> #sub
+        },
+    };
+    if options.parallel.is_some() {
+        // Inputs are shared among threads, and output elements and partial sums are sent
+        let storages = super::storages(subscripts.inputs.len());
+        tt.extend(quote! {
+            , T: Send + Sync #(, #storages: Sync)*
+        });
+        if let Some(acc) = &options.accumulate {
+            tt.extend(quote! { , #acc: Send });
+        }
+    }
+    tt
 }
 
 /// Define the index size identifiers, e.g. `n_i`
@@ -105,8 +657,9 @@ pub fn define_array_size(subscripts: &Subscripts) -> TokenStream2 {
                 }
             })
             .collect();
+        let n_ident = tuple(n_ident.iter().map(|n| quote! { #n }));
         tt.push(quote! {
-            let (#(#n_ident),*) = #arg.dim();
+            let #n_ident = #arg.dim();
         });
     }
     quote! { #(#tt)* }
@@ -122,34 +675,55 @@ pub fn array_size_asserts(subscripts: &Subscripts) -> TokenStream2 {
             .collect();
         // size of index defined previously, e.g. `n_i`
         let n: Vec<_> = arg.indices().into_iter().map(n_ident).collect();
+        let dim = tuple(n_each.iter().map(|n| quote! { #n }));
         tt.push(quote! {
-            let (#(#n_each),*) = #arg.dim();
+            let #dim = #arg.dim();
             #(assert_eq!(#n_each, #n);)*
         });
     }
     quote! { #({ #tt })* }
 }
 
-fn define_output_array(subscripts: &Subscripts) -> TokenStream2 {
-    // Define output array
+/// Allocate the output array filled with zeros, see [crate::codegen::EinsumBackend::alloc]
+pub fn define_output_array(subscripts: &Subscripts) -> TokenStream2 {
     let output_ident = &subscripts.output;
-    let mut n_output = Vec::new();
-    for i in subscripts.output.indices() {
-        n_output.push(n_ident(i));
+    let shape = tuple(subscripts.output.indices().into_iter().map(|i| {
+        let n = n_ident(i);
+        quote! { #n }
+    }));
+    let zero = zero_tt(&quote! { T });
+    quote! {
+        let mut #output_ident = ndarray::Array::<T, _>::from_elem(#shape, #zero);
     }
+}
+
+/// Helper functions used in [contraction] for the summation algorithms of `options`
+pub fn helpers(options: &Options) -> TokenStream2 {
+    let pairwise_sum_tt = match options.summation {
+        Summation::Pairwise => Some(pairwise_sum_definition()),
+        _ => None,
+    };
+    let tree_sum_tt = if options.deterministic {
+        Some(tree_sum_definition())
+    } else {
+        None
+    };
     quote! {
-        let mut #output_ident = ndarray::Array::zeros((#(#n_output),*));
+        #pairwise_sum_tt
+        #tree_sum_tt
     }
 }
 
 /// Actual component of einsum [function_definition]
-pub fn inner(subscripts: &Subscripts) -> TokenStream2 {
+pub fn inner(subscripts: &Subscripts, elem_type: &ElemType, options: &Options) -> TokenStream2 {
+    let helpers = helpers(options);
     let array_size = define_array_size(subscripts);
     let array_size_asserts = array_size_asserts(subscripts);
     let output_ident = &subscripts.output;
     let output_tt = define_output_array(subscripts);
-    let contraction_tt = contraction(subscripts);
+    let contraction_tt = contraction(subscripts, elem_type, options);
     quote! {
+        #helpers
         #array_size
         #array_size_asserts
         #output_tt
@@ -160,7 +734,10 @@ pub fn inner(subscripts: &Subscripts) -> TokenStream2 {
 
 #[cfg(test)]
 mod test {
-    use crate::{codegen::format_block, *};
+    use crate::{
+        codegen::{format_block, ndarray::ElemType},
+        *,
+    };
 
     #[test]
     fn define_array_size() {
@@ -177,12 +754,83 @@ mod test {
     fn contraction() {
         let mut namespace = Namespace::init();
         let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
-        let tt = format_block(super::contraction(&subscripts).to_string());
+        let tt = format_block(
+            super::contraction(&subscripts, &ElemType::Product, &Default::default()).to_string(),
+        );
         insta::assert_snapshot!(tt, @r###"
         for a in 0..n_a {
-            for c in 0..n_c {
+            for b in 0..n_b {
+                let prod0 = arg0[(a, b)].clone();
+                let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .for_each(|out_elem, elem1| {
+                        (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                    });
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_layout_dispatch() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let options = super::Options {
+            layout_dispatch: true,
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        match (
+            arg0.strides()[0].unsigned_abs() < arg0.strides()[1].unsigned_abs(),
+            arg1.strides()[0].unsigned_abs() < arg1.strides()[1].unsigned_abs(),
+        ) {
+            (true, false) => {
                 for b in 0..n_b {
-                    out0[(a, c)] = arg0[(a, b)] * arg1[(b, c)];
+                    for a in 0..n_a {
+                        let prod0 = arg0[(a, b)].clone();
+                        let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                        let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                        ndarray::Zip::from(lane0)
+                            .and(lane1)
+                            .for_each(|out_elem, elem1| {
+                                (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                            });
+                    }
+                }
+            }
+            (false, true) | (true, true) => {
+                for a in 0..n_a {
+                    for c in 0..n_c {
+                        let mut sum = out0[(a, c)].clone();
+                        let lane0 = arg0.slice(ndarray::s![a, 0..n_b]);
+                        let lane1 = arg1.slice(ndarray::s![0..n_b, c]);
+                        sum =
+                            ndarray::Zip::from(lane0)
+                                .and(lane1)
+                                .fold(sum, |mut sum, elem0, elem1| {
+                                    sum = sum + elem0.clone() * elem1.clone();
+                                    sum
+                                });
+                        out0[(a, c)] = sum;
+                    }
+                }
+            }
+            _ => {
+                for a in 0..n_a {
+                    for b in 0..n_b {
+                        let prod0 = arg0[(a, b)].clone();
+                        let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                        let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                        ndarray::Zip::from(lane0)
+                            .and(lane1)
+                            .for_each(|out_elem, elem1| {
+                                (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                            });
+                    }
                 }
             }
         }
@@ -193,7 +841,9 @@ mod test {
     fn inner() {
         let mut namespace = Namespace::init();
         let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
-        let tt = format_block(super::inner(&subscripts).to_string());
+        let tt = format_block(
+            super::inner(&subscripts, &ElemType::Product, &Default::default()).to_string(),
+        );
         insta::assert_snapshot!(tt, @r###"
         let (n_a, n_b) = arg0.dim();
         let (_, n_c) = arg1.dim();
@@ -207,15 +857,352 @@ mod test {
             assert_eq!(n_0, n_b);
             assert_eq!(n_1, n_c);
         }
-        let mut out0 = ndarray::Array::zeros((n_a, n_c));
+        let mut out0 = ndarray::Array::<T, _>::from_elem(
+            (n_a, n_c),
+            <T as core::iter::Sum>::sum(core::iter::empty()),
+        );
+        for a in 0..n_a {
+            for b in 0..n_b {
+                let prod0 = arg0[(a, b)].clone();
+                let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .for_each(|out_elem, elem1| {
+                        (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                    });
+            }
+        }
+        out0
+        "###);
+    }
+
+    #[test]
+    fn contraction_accumulate() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let options = super::Options {
+            accumulate: Some(syn::parse_quote! { f64 }),
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        for a in 0..n_a {
+            for c in 0..n_c {
+                let mut sum = <f64 as core::iter::Sum>::sum(core::iter::empty());
+                let lane0 = arg0.slice(ndarray::s![a, 0..n_b]);
+                let lane1 = arg1.slice(ndarray::s![0..n_b, c]);
+                sum = ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .fold(sum, |mut sum, elem0, elem1| {
+                        sum += <f64>::from(elem0.clone()) * <f64>::from(elem1.clone());
+                        sum
+                    });
+                out0[(a, c)] = num_traits::AsPrimitive::<T>::as_(sum);
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_kahan() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let options = super::Options {
+            summation: super::Summation::Kahan,
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
         for a in 0..n_a {
             for c in 0..n_c {
+                let mut sum = <T as core::iter::Sum>::sum(core::iter::empty());
+                let mut compensation = <T as core::iter::Sum>::sum(core::iter::empty());
+                let lane0 = arg0.slice(ndarray::s![a, 0..n_b]);
+                let lane1 = arg1.slice(ndarray::s![0..n_b, c]);
+                (sum, compensation) = ndarray::Zip::from(lane0).and(lane1).fold(
+                    (sum, compensation),
+                    |(mut sum, mut compensation), elem0, elem1| {
+                        let y = elem0.clone() * elem1.clone() - compensation;
+                        let t = sum.clone() + y.clone();
+                        compensation = (t.clone() - sum) - y;
+                        sum = t;
+                        (sum, compensation)
+                    },
+                );
+                out0[(a, c)] = sum;
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_pairwise() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ijk,jk->i").unwrap();
+        let options = super::Options {
+            summation: super::Summation::Pairwise,
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        for a in 0..n_a {
+            let sum = pairwise_sum::<T, _>(0, n_c * n_b, &|l: usize| {
+                let c = l % n_c;
+                let b = l / (n_c) % n_b;
+                arg0[(a, b, c)].clone() * arg1[(b, c)].clone()
+            });
+            out0[a] = sum;
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_deterministic() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ijk,jk->i").unwrap();
+        let options = super::Options {
+            deterministic: true,
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        for a in 0..n_a {
+            let n_terms = n_c * n_b;
+            let sum = tree_sum::<T, _>(0, n_terms.div_ceil(1024usize), &|chunk: usize| {
+                let begin = chunk * 1024usize;
+                let end = (begin + 1024usize).min(n_terms);
+                {
+                    let mut sum = <T as core::iter::Sum>::sum(core::iter::empty());
+                    for l in begin..end {
+                        let c = l % n_c;
+                        let b = l / (n_c) % n_b;
+                        sum = sum + arg0[(a, b, c)].clone() * arg1[(b, c)].clone();
+                    }
+                    sum
+                }
+            });
+            out0[a] = sum;
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_parallel() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let options = super::Options {
+            parallel: Some(super::PARALLEL_THRESHOLD),
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        if n_a * n_c * n_b >= 65536usize {
+            use ndarray::parallel::prelude::*;
+            out0.axis_iter_mut(ndarray::Axis(0))
+                .into_par_iter()
+                .enumerate()
+                .for_each(|(a, mut out0)| {
+                    for b in 0..n_b {
+                        let prod0 = arg0[(a, b)].clone();
+                        let lane0 = out0.slice_mut(ndarray::s![0..n_c]);
+                        let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                        ndarray::Zip::from(lane0)
+                            .and(lane1)
+                            .for_each(|out_elem, elem1| {
+                                (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                            });
+                    }
+                });
+        } else {
+            for a in 0..n_a {
                 for b in 0..n_b {
-                    out0[(a, c)] = arg0[(a, b)] * arg1[(b, c)];
+                    let prod0 = arg0[(a, b)].clone();
+                    let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                    let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                    ndarray::Zip::from(lane0)
+                        .and(lane1)
+                        .for_each(|out_elem, elem1| {
+                            (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                        });
                 }
             }
         }
-        out0
+        "###);
+
+        // Chunks of deterministic summation run in parallel for 0-rank output
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "i,i->").unwrap();
+        let options = super::Options {
+            deterministic: true,
+            parallel: Some(super::PARALLEL_THRESHOLD),
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        let n_terms = n_a;
+        let sum = if n_terms >= 65536usize {
+            use ndarray::parallel::prelude::*;
+            let partial: alloc::vec::Vec<T> = (0..n_terms.div_ceil(1024usize))
+                .into_par_iter()
+                .map(|chunk: usize| {
+                    let begin = chunk * 1024usize;
+                    let end = (begin + 1024usize).min(n_terms);
+                    {
+                        let mut sum = <T as core::iter::Sum>::sum(core::iter::empty());
+                        for l in begin..end {
+                            let a = l % n_a;
+                            sum = sum + arg0[a].clone() * arg1[a].clone();
+                        }
+                        sum
+                    }
+                })
+                .collect();
+            tree_sum::<T, _>(0, partial.len(), &|chunk: usize| partial[chunk].clone())
+        } else {
+            tree_sum::<T, _>(0, n_terms.div_ceil(1024usize), &|chunk: usize| {
+                let begin = chunk * 1024usize;
+                let end = (begin + 1024usize).min(n_terms);
+                {
+                    let mut sum = <T as core::iter::Sum>::sum(core::iter::empty());
+                    for l in begin..end {
+                        let a = l % n_a;
+                        sum = sum + arg0[a].clone() * arg1[a].clone();
+                    }
+                    sum
+                }
+            })
+        };
+        out1[()] = sum;
+        "###);
+    }
+
+    #[test]
+    fn loop_order() {
+        use super::Layout::{C, F};
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let indices = ['a', 'c', 'b'];
+        assert_eq!(
+            super::loop_order(&subscripts, &[C, C], &indices),
+            ['a', 'b', 'c']
+        );
+        assert_eq!(
+            super::loop_order(&subscripts, &[F, C], &indices),
+            ['b', 'a', 'c']
+        );
+        // Same score for all indices, and keeps the given order
+        assert_eq!(
+            super::loop_order(&subscripts, &[F, F], &indices),
+            ['a', 'c', 'b']
+        );
+    }
+
+    #[test]
+    fn contraction_tiled() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let options = super::Options {
+            tile: Some(super::Tile::Fixed(16)),
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        for a_tile in (0..n_a).step_by(16usize) {
+            for b_tile in (0..n_b).step_by(16usize) {
+                for c_tile in (0..n_c).step_by(16usize) {
+                    for a in a_tile..(a_tile + 16usize).min(n_a) {
+                        for b in b_tile..(b_tile + 16usize).min(n_b) {
+                            let prod0 = arg0[(a, b)].clone();
+                            let lane0 =
+                                out0.slice_mut(ndarray::s![a, c_tile..(c_tile + 16usize).min(n_c)]);
+                            let lane1 = arg1.slice(ndarray::s![b, c_tile..(c_tile + 16usize).min(n_c)]);
+                            ndarray::Zip::from(lane0)
+                                .and(lane1)
+                                .for_each(|out_elem, elem1| {
+                                    (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                                });
+                        }
+                    }
+                }
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn heuristic_tile() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        assert_eq!(super::heuristic_tile(&subscripts, &['a', 'b', 'c']), 32);
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "pqrs,pi->iqrs").unwrap();
+        assert_eq!(
+            super::heuristic_tile(&subscripts, &['a', 'b', 'c', 'd', 'e']),
+            4
+        );
+    }
+
+    #[test]
+    fn contraction_hoisted() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "i,ij,j->i").unwrap();
+        let tt = format_block(
+            super::contraction(&subscripts, &ElemType::Product, &Default::default()).to_string(),
+        );
+        insta::assert_snapshot!(tt, @r###"
+        for a in 0..n_a {
+            let prod0 = arg0[a].clone();
+            let mut sum = out0[a].clone();
+            let lane0 = arg1.slice(ndarray::s![a, 0..n_b]);
+            let lane1 = arg2.slice(ndarray::s![0..n_b]);
+            sum = ndarray::Zip::from(lane0)
+                .and(lane1)
+                .fold(sum, |mut sum, elem1, elem2| {
+                    sum = sum + prod0.clone() * elem1.clone() * elem2.clone();
+                    sum
+                });
+            out0[a] = sum;
+        }
+        "###);
+    }
+
+    #[test]
+    fn contraction_unchecked() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,j->i").unwrap();
+        let options = super::Options {
+            unchecked: true,
+            ..Default::default()
+        };
+        let tt =
+            format_block(super::contraction(&subscripts, &ElemType::Product, &options).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        #[allow(unused_doc_comments)]
+        #[doc = " SAFETY: All the indices passed to `uget` and `uget_mut` in this block are in bounds:"]
+        #[doc = " - `arg0.dim()` is `(n_a, n_b)` by `assert_eq!(n_0, n_a)`, `assert_eq!(n_1, n_b)` above"]
+        #[doc = " - `arg1.dim()` is `n_b` by `assert_eq!(n_0, n_b)` above"]
+        #[doc = " - `out0` is created with the shape `n_a`"]
+        #[doc = " - The loops, tiles and flattened indices decoded by `l / stride % n` run `a` in `0..n_a`, `b` in `0..n_b`"]
+        unsafe {
+            for a in 0..n_a {
+                let mut sum = (*out0.uget_mut(a)).clone();
+                let lane0 = arg0.slice(ndarray::s![a, 0..n_b]);
+                let lane1 = arg1.slice(ndarray::s![0..n_b]);
+                sum = ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .fold(sum, |mut sum, elem0, elem1| {
+                        sum = sum + elem0.clone() * elem1.clone();
+                        sum
+                    });
+                (*out0.uget_mut(a)) = sum;
+            }
+        }
         "###);
     }
 }
@@ -0,0 +1,214 @@
+//! Named public functions computing einsum, e.g. `pub fn bmm` for `bij,bjk->bik`
+//!
+//! Unlike the expansion of `einsum!`, the function is generic over the element type `T` shared by all operands,
+//! since the bounds of the steps have to be implied by the bounds of the function.
+
+use super::{dim, naive, storages, ElemType, Ndarray};
+use crate::{codegen, Path};
+use anyhow::{bail, Result};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+
+/// Documentation of the function, which shows the subscripts and the path
+///
+/// Each line is a line of the doc comment, e.g.
+///
+/// ```text
+/// einsum `ij,jk,kl->il`
+///
+/// Computed along the path of 2 steps, which requires O(N^3) operations and O(N^2) memory:
+///
+/// - `ab,bc->ac | arg0,arg1->out1`
+/// - `ab,bc->ac | out1,arg2->out0`
+/// ```
+pub fn path_doc(subscripts: &str, path: &Path) -> Vec<String> {
+    let mut lines = vec![
+        format!("einsum `{}`", subscripts),
+        String::new(),
+        format!(
+            "Computed along the path of {} step{}, which requires O(N^{}) operations and O(N^{}) memory:",
+            path.len(),
+            if path.len() == 1 { "" } else { "s" },
+            path.compute_order(),
+            path.memory_order()
+        ),
+        String::new(),
+    ];
+    lines.extend(path.iter().map(|ss| format!("- `{}`", ss)));
+    lines
+}
+
+/// Public function computing einsum `subscripts` along `path`, e.g. for `bij,bjk->bik`
+///
+/// ```ignore
+/// /// einsum `bij,bjk->bik`
+/// /// ...
+/// pub fn bmm<T, S0, S1>(
+///     arg0: ndarray::ArrayBase<S0, ndarray::Ix3>,
+///     arg1: ndarray::ArrayBase<S1, ndarray::Ix3>,
+/// ) -> ndarray::Array<T, ndarray::Ix3>
+/// where
+///     T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+///     S0: ndarray::Data<Elem = T>,
+///     S1: ndarray::Data<Elem = T>,
+/// {
+///     // The block of `einsum!`
+/// }
+/// ```
+///
+/// `core::iter::Sum` is required only for the zero as the sum of no elements,
+/// which must be the additive identity, see [naive::bounds].
+/// The output is `T` for 0-rank output, or `ndarray::ArrayD<T>` with [naive::Options::into_dyn].
+/// [naive::Options::accumulate] is not supported since it requires conversions among the element types,
+/// and [naive::Options::from_dyn] is not since the arguments have the static ranks.
+pub fn named_function(
+    vis: &syn::Visibility,
+    name: &syn::Ident,
+    subscripts: &str,
+    path: &Path,
+    options: &naive::Options,
+) -> Result<TokenStream2> {
+    if options.accumulate.is_some() {
+        bail!("accumulate option is not supported for named einsum functions");
+    }
+    if options.from_dyn {
+        bail!("from_dyn option is not supported for named einsum functions");
+    }
+    let n = path.num_args();
+    let args: Vec<syn::Ident> = (0..n).map(|n| format_ident!("arg{}", n)).collect();
+    let storages = storages(n);
+    let dims = path
+        .original()
+        .inputs
+        .iter()
+        .map(|arg| dim(arg.indices().len()));
+    let rank = path.output().indices().len();
+    let out_ty = if options.into_dyn {
+        quote! { ndarray::ArrayD<T> }
+    } else if rank == 0 {
+        quote! { T }
+    } else {
+        let dim = dim(rank);
+        quote! { ndarray::Array<T, #dim> }
+    };
+
+    let mut bounds = vec![
+        quote! { Clone },
+        quote! { core::iter::Sum },
+        quote! { core::ops::Add<Output = T> },
+        quote! { core::ops::Mul<Output = T> },
+    ];
+    if options.summation == naive::Summation::Kahan {
+        bounds.push(quote! { core::ops::Sub<Output = T> });
+    }
+    let parallel = options.parallel.is_some();
+    if parallel {
+        bounds.push(quote! { Send });
+        bounds.push(quote! { Sync });
+    }
+    if options.runtime {
+        bounds.push(quote! { 'static });
+    }
+    let sync = parallel.then(|| quote! { + Sync });
+
+    let arg_exprs: Vec<syn::Expr> = args.iter().map(|arg| syn::parse_quote! { #arg }).collect();
+    let body = if options.runtime {
+        codegen::runtime::check_options(&ElemType::Product, options)?;
+        codegen::runtime::einsum(path, &arg_exprs, options.into_dyn)
+    } else {
+        let backend = Ndarray {
+            elem_type: ElemType::Product,
+            options: options.clone(),
+        };
+        codegen::einsum(&backend, path, &arg_exprs)
+    };
+    let doc = path_doc(subscripts, path)
+        .into_iter()
+        .map(|line| format!(" {}", line));
+    Ok(quote! {
+        #(#[doc = #doc])*
+        // Lints in the modules generated by build scripts,
+        // e.g. the step functions named as `ab_bc__ac` and `let out0 = ...; out0`
+        #[allow(non_snake_case, clippy::let_and_return)]
+        #vis fn #name<T, #(#storages),*>(
+            #( #args: ndarray::ArrayBase<#storages, #dims> ),*
+        ) -> #out_ty
+        where
+            T: #(#bounds)+*,
+            #( #storages: ndarray::Data<Elem = T> #sync ),*
+        #body
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegen::format_block;
+
+    #[test]
+    fn doc() {
+        let path = Path::brute_force("ij,jk,kl->il").unwrap();
+        insta::assert_snapshot!(path_doc("ij,jk,kl->il", &path).join("\n"), @r###"
+        einsum `ij,jk,kl->il`
+
+        Computed along the path of 2 steps, which requires O(N^3) operations and O(N^2) memory:
+
+        - `ab,bc->ac | arg0,arg1->out1`
+        - `ab,bc->ac | out1,arg2->out0`
+        "###);
+    }
+
+    #[test]
+    fn trace() {
+        let path = Path::brute_force("ii->").unwrap();
+        let tt = named_function(
+            &syn::parse_quote! { pub },
+            &syn::parse_quote! { trace },
+            "ii->",
+            &path,
+            &Default::default(),
+        )
+        .unwrap();
+        insta::assert_snapshot!(format_block(tt.to_string()), @r###"
+        #[doc = " einsum `ii->`"]
+        #[doc = " "]
+        #[doc = " Computed along the path of 1 step, which requires O(N^1) operations and O(N^0) memory:"]
+        #[doc = " "]
+        #[doc = " - `aa-> | arg0->out0`"]
+        #[allow(non_snake_case, clippy::let_and_return)]
+        pub fn trace<T, S0>(arg0: ndarray::ArrayBase<S0, ndarray::Ix2>) -> T
+        where
+            T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            S0: ndarray::Data<Elem = T>,
+        {
+            fn aa__<T, S0>(
+                arg0: ndarray::ArrayBase<S0, ndarray::Ix2>,
+            ) -> ndarray::Array<T, ndarray::Ix0>
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+                S0: ndarray::Data<Elem = T>,
+            {
+                let (n_a, _) = arg0.dim();
+                {
+                    let (n_0, n_1) = arg0.dim();
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_a);
+                }
+                let mut out0 = ndarray::Array::<T, _>::from_elem(
+                    (),
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
+                let mut sum = out0[()].clone();
+                for a in 0..n_a {
+                    sum = sum + arg0[(a, a)].clone();
+                }
+                out0[()] = sum;
+                out0
+            }
+            let arg0 = arg0;
+            let out0 = aa__(arg0);
+            out0.into_scalar()
+        }
+        "###);
+    }
+}
@@ -0,0 +1,643 @@
+//! Print [crate::ir] into tokens shared among backends
+
+use super::{
+    ndarray::{
+        naive::{Options, Summation, DETERMINISTIC_CHUNK},
+        ElemType,
+    },
+    EinsumBackend,
+};
+use crate::{
+    ir::{Access, Array, Expr, Kernel, Loop, Range, Stmt, Target},
+    Subscripts,
+};
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+/// Printer of [Kernel] into statements
+///
+/// The inputs are the arguments of the einsum function indexed by [EinsumBackend::index],
+/// and the elements are converted into the type of products determined by `elem_type` and `options`.
+/// The parallel loops and [Lane]s are printed for ndarray.
+pub(crate) struct Printer<'a> {
+    subscripts: &'a Subscripts,
+    elem_type: &'a ElemType,
+    options: &'a Options,
+    backend: &'a dyn EinsumBackend,
+    /// Loop over the lanes in the innermost loop
+    lanes: bool,
+    /// Index of the parallel loop, where the output is shadowed by its subview
+    shadowed: Option<char>,
+}
+
+impl<'a> Printer<'a> {
+    pub(crate) fn new(
+        subscripts: &'a Subscripts,
+        elem_type: &'a ElemType,
+        options: &'a Options,
+        backend: &'a dyn EinsumBackend,
+    ) -> Self {
+        Printer {
+            subscripts,
+            elem_type,
+            options,
+            backend,
+            lanes: false,
+            shadowed: None,
+        }
+    }
+
+    /// Print the innermost loops over lanes of ndarray, see [Lane]
+    pub(crate) fn with_lanes(mut self) -> Self {
+        self.lanes = true;
+        self
+    }
+
+    pub(crate) fn kernel(&mut self, kernel: &Kernel) -> TokenStream2 {
+        self.stmts(&kernel.body)
+    }
+
+    pub(crate) fn stmts(&mut self, stmts: &[Stmt]) -> TokenStream2 {
+        let stmts = stmts.iter().map(|stmt| self.stmt(stmt));
+        quote! { #(#stmts)* }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> TokenStream2 {
+        let local = local_type(self.options);
+        match stmt {
+            Stmt::Loop(l) => self.for_loop(l),
+            Stmt::Let { var, value } => {
+                let var = quote::format_ident!("{}", var);
+                let value = self.expr(value, &[]);
+                quote! { let #var = #value; }
+            }
+            Stmt::Accumulator { init: None } => sum_step_tt(self.options, &local, &quote! {}).0,
+            Stmt::Accumulator { init: Some(access) } => {
+                let elem = self.elem(access);
+                let zero = zero_tt(&local);
+                let compensation = match self.options.summation {
+                    Summation::Kahan => Some(quote! {
+                        let mut compensation = #zero;
+                    }),
+                    _ => None,
+                };
+                quote! {
+                    let mut sum = #elem.clone();
+                    #compensation
+                }
+            }
+            Stmt::Accumulate { target, value } => {
+                let value = self.expr(value, &[]);
+                self.accumulate(target, None, value)
+            }
+            Stmt::Store(access) => {
+                let elem = self.elem(access);
+                let store = store_tt(self.options);
+                quote! { #elem = #store; }
+            }
+            Stmt::Reduce {
+                indices,
+                value,
+                target,
+                parallel,
+            } => {
+                let value = self.expr(value, &[]);
+                let elem = self.elem(target);
+                let parallel = if *parallel {
+                    self.options.parallel
+                } else {
+                    None
+                };
+                local_sum_tt(self.options, &value, &elem, indices, parallel)
+            }
+        }
+    }
+
+    fn for_loop(&mut self, l: &Loop) -> TokenStream2 {
+        let i = l.index;
+        let index = index_ident(i);
+        let n = n_ident(i);
+        if l.parallel {
+            let output = &self.subscripts.output;
+            let shadowed = self.shadowed.replace(i);
+            let body = self.stmts(&l.body);
+            self.shadowed = shadowed;
+            return quote! {
+                use ndarray::parallel::prelude::*;
+                #output
+                    .axis_iter_mut(ndarray::Axis(0))
+                    .into_par_iter()
+                    .enumerate()
+                    .for_each(|(#index, mut #output)| {
+                        #body
+                    });
+            };
+        }
+        let range = match l.range {
+            Range::Full => quote! { 0..#n },
+            Range::Tile(tile) => {
+                let t = tile_ident(i);
+                quote! { #t..(#t + #tile).min(#n) }
+            }
+            Range::Tiles(tile) => {
+                let t = tile_ident(i);
+                let body = self.stmts(&l.body);
+                return quote! {
+                    for #t in (0..#n).step_by(#tile) { #body }
+                };
+            }
+        };
+        if let Some(lane) = self.lane(l).filter(|_| self.lanes) {
+            return lane.contraction_for(i, &range);
+        }
+        let body = self.stmts(&l.body);
+        quote! {
+            for #index in #range { #body }
+        }
+    }
+
+    /// Statement adding `value` to the target
+    ///
+    /// `lane_elem` is the reference to the output element in [Lane] if it is iterated.
+    fn accumulate(
+        &self,
+        target: &Target,
+        lane_elem: Option<TokenStream2>,
+        value: TokenStream2,
+    ) -> TokenStream2 {
+        match target {
+            Target::Elem(access) => {
+                let elem = lane_elem.unwrap_or_else(|| self.elem(access));
+                quote! {
+                    #elem = #elem.clone() + #value;
+                }
+            }
+            Target::Accumulator => sum_step_tt(self.options, &local_type(self.options), &value).1,
+        }
+    }
+
+    /// Lane of the innermost loop `l` accumulating the products of the elements along it
+    ///
+    /// Returns `None` if some array has the index twice, or no input or more than five inputs have it.
+    fn lane(&self, l: &Loop) -> Option<Lane> {
+        let (target, value) = match l.body.as_slice() {
+            [Stmt::Accumulate { target, value }] => (target, value),
+            _ => return None,
+        };
+        let i = l.index;
+        let count = |indices: &[char]| indices.iter().filter(|&&j| j == i).count();
+        let mut operands = Vec::new();
+        let mut lanes = Vec::new();
+        for factor in value.factors() {
+            if let Expr::Load(access) = factor {
+                let k = match access.array {
+                    Array::Input(k) => k,
+                    Array::Output => continue,
+                };
+                match count(&access.indices) {
+                    0 => {}
+                    1 => {
+                        let arg = &self.subscripts.inputs[k];
+                        lanes.push(k);
+                        operands.push(LaneOperand {
+                            array: quote! { #arg },
+                            indices: access.indices.clone(),
+                            mutable: false,
+                            elem: quote::format_ident!("elem{}", k),
+                        });
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        // `ndarray::Zip` takes at most six producers, one of which may be the output
+        if operands.is_empty() || operands.len() > 5 {
+            return None;
+        }
+        let mut lane_elem = None;
+        if let Target::Elem(access) = target {
+            let indices = self.indices(access);
+            if count(&indices) == 1 {
+                let output = &self.subscripts.output;
+                let elem = quote::format_ident!("out_elem");
+                lane_elem = Some(quote! { (*#elem) });
+                operands.insert(
+                    0,
+                    LaneOperand {
+                        array: quote! { #output },
+                        indices,
+                        mutable: true,
+                        elem,
+                    },
+                );
+            }
+        }
+        let value = self.expr(value, &lanes);
+        let state = match (target, self.options.summation) {
+            (Target::Elem(_), _) => Vec::new(),
+            (Target::Accumulator, Summation::Kahan) => vec![
+                quote::format_ident!("sum"),
+                quote::format_ident!("compensation"),
+            ],
+            (Target::Accumulator, _) => vec![quote::format_ident!("sum")],
+        };
+        Some(Lane {
+            operands,
+            state,
+            inner: self.accumulate(target, lane_elem, value),
+        })
+    }
+
+    /// Indices of the access, where the shadowed index of the output is removed
+    fn indices(&self, access: &Access) -> Vec<char> {
+        match access.array {
+            Array::Input(_) => access.indices.clone(),
+            Array::Output => access
+                .indices
+                .iter()
+                .cloned()
+                .filter(|&i| Some(i) != self.shadowed)
+                .collect(),
+        }
+    }
+
+    /// Element of the array by [EinsumBackend::index], e.g. `arg0[(a, b)]`
+    fn elem(&self, access: &Access) -> TokenStream2 {
+        let indices: Vec<syn::Ident> = self.indices(access).into_iter().map(index_ident).collect();
+        let (array, mutable) = match access.array {
+            Array::Input(k) => {
+                let arg = &self.subscripts.inputs[k];
+                (quote! { #arg }, false)
+            }
+            Array::Output => {
+                let output = &self.subscripts.output;
+                (quote! { #output }, true)
+            }
+        };
+        self.backend.index(&array, &indices, mutable)
+    }
+
+    /// Expression of the value, where the elements of `lanes` inputs are taken from [Lane]
+    fn expr(&self, expr: &Expr, lanes: &[usize]) -> TokenStream2 {
+        match expr {
+            Expr::Load(access) => {
+                let elem = match access.array {
+                    Array::Input(k) if lanes.contains(&k) => {
+                        let elem = quote::format_ident!("elem{}", k);
+                        quote! { #elem.clone() }
+                    }
+                    _ => {
+                        let elem = self.elem(access);
+                        quote! { #elem.clone() }
+                    }
+                };
+                factor_tt(self.elem_type, self.options, elem)
+            }
+            Expr::Var(var) => {
+                let var = quote::format_ident!("{}", var);
+                // Accumulation type is `Copy` as required by `num_traits::AsPrimitive`
+                match &self.options.accumulate {
+                    Some(_) => quote! { #var },
+                    None => quote! { #var.clone() },
+                }
+            }
+            Expr::Mul(factors) => {
+                let factors: Vec<TokenStream2> =
+                    factors.iter().map(|f| self.expr(f, lanes)).collect();
+                let (first, rest) = factors.split_first().expect("product of no factors");
+                quote! { #first #(* #rest)* }
+            }
+        }
+    }
+}
+
+pub(crate) fn index_ident(i: char) -> syn::Ident {
+    quote::format_ident!("{}", i)
+}
+
+pub(crate) fn n_ident(i: char) -> syn::Ident {
+    quote::format_ident!("n_{}", i)
+}
+
+fn tile_ident(i: char) -> syn::Ident {
+    quote::format_ident!("{}_tile", i)
+}
+
+/// Convert the input element into the type of products
+fn factor_tt(elem_type: &ElemType, options: &Options, elem: TokenStream2) -> TokenStream2 {
+    match (&options.accumulate, elem_type) {
+        (Some(acc), _) => quote! { <#acc>::from(#elem) },
+        (None, ElemType::Product) => elem,
+        (None, ElemType::Explicit(_)) => quote! { T::from(#elem) },
+    }
+}
+
+/// Array iterated along the innermost index in [Lane]
+struct LaneOperand {
+    array: TokenStream2,
+    /// Indices of the array, which is shadowed by its subview in parallel loops
+    indices: Vec<char>,
+    mutable: bool,
+    /// Reference to the element in the lane
+    elem: syn::Ident,
+}
+
+/// Loop over the lanes of the arrays along the innermost index by `ndarray::Zip`
+///
+/// `Zip` iterates over the slices if all the lanes are contiguous, which is vectorized by LLVM
+/// while the multi-index access in the loop is hardly vectorized,
+/// and falls back to the strided iteration otherwise.
+struct Lane {
+    operands: Vec<LaneOperand>,
+    /// Local variables updated in the loop, which are passed through `Zip::fold`
+    /// since the closure cannot move them
+    state: Vec<syn::Ident>,
+    /// Statements in the loop using the elements of [LaneOperand::elem]
+    inner: TokenStream2,
+}
+
+impl Lane {
+    /// Loop over the lanes of the index `i` in `range`
+    fn contraction_for(&self, i: char, range: &TokenStream2) -> TokenStream2 {
+        let mut lets = Vec::new();
+        let mut lanes = Vec::new();
+        for (k, operand) in self.operands.iter().enumerate() {
+            let lane = quote::format_ident!("lane{}", k);
+            let array = &operand.array;
+            let items = operand.indices.iter().map(|&j| {
+                if j == i {
+                    range.clone()
+                } else {
+                    let index = index_ident(j);
+                    quote! { #index }
+                }
+            });
+            if operand.mutable {
+                lets.push(quote! { let #lane = #array.slice_mut(ndarray::s![#(#items),*]); });
+            } else {
+                lets.push(quote! { let #lane = #array.slice(ndarray::s![#(#items),*]); });
+            }
+            lanes.push(lane);
+        }
+        let (first, rest) = lanes.split_first().expect("lane of no operands");
+        let elems = self.operands.iter().map(|operand| &operand.elem);
+        let inner = &self.inner;
+        if self.state.is_empty() {
+            return quote! {
+                #(#lets)*
+                ndarray::Zip::from(#first)
+                    #(.and(#rest))*
+                    .for_each(|#(#elems),*| { #inner });
+            };
+        }
+        let state = tuple(self.state.iter().map(|var| quote! { #var }));
+        let pattern = tuple(self.state.iter().map(|var| quote! { mut #var }));
+        quote! {
+            #(#lets)*
+            #state = ndarray::Zip::from(#first)
+                #(.and(#rest))*
+                .fold(#state, |#pattern, #(#elems),*| {
+                    #inner
+                    #state
+                });
+        }
+    }
+}
+
+/// Tuple of the items, or the item itself for a single item
+pub(crate) fn tuple(items: impl Iterator<Item = TokenStream2>) -> TokenStream2 {
+    let items: Vec<TokenStream2> = items.collect();
+    match items.as_slice() {
+        [item] => item.clone(),
+        _ => quote! { (#(#items),*) },
+    }
+}
+
+/// Loops for column-major or row-major arrays, where the indices with larger strides come outer,
+/// printed by the backend without lanes
+pub(crate) fn strided_loops(
+    subscripts: &Subscripts,
+    backend: &dyn EinsumBackend,
+    column_major: bool,
+) -> TokenStream2 {
+    // Sum of the orders of strides in the arrays, which is larger for larger strides
+    let score = |i: char| -> usize {
+        subscripts
+            .inputs
+            .iter()
+            .chain(std::iter::once(&subscripts.output))
+            .filter_map(|arg| {
+                let indices = arg.indices();
+                let p = indices.iter().position(|&j| j == i)?;
+                Some(if column_major {
+                    p
+                } else {
+                    indices.len() - 1 - p
+                })
+            })
+            .sum()
+    };
+    let mut kernel = Kernel::lower(subscripts);
+    let contraction_indices: Vec<char> = subscripts.contraction_indices().into_iter().collect();
+    let mut order = kernel.output.clone();
+    order.extend(contraction_indices.iter().cloned());
+    order.sort_by_key(|&i| std::cmp::Reverse(score(i)));
+    kernel.interchange(&order);
+    kernel.hoist();
+    if !contraction_indices.is_empty() {
+        kernel.accumulate_locally(true);
+    }
+    let options = Options::default();
+    Printer::new(subscripts, &ElemType::Product, &options, backend).kernel(&kernel)
+}
+
+/// Type of the local variable summing up the products
+fn local_type(options: &Options) -> TokenStream2 {
+    match &options.accumulate {
+        Some(acc) => quote! { #acc },
+        None => quote! { T },
+    }
+}
+
+/// Expression converting the local variable `sum` into the output element
+fn store_tt(options: &Options) -> TokenStream2 {
+    match &options.accumulate {
+        Some(_) => quote! { num_traits::AsPrimitive::<T>::as_(sum) },
+        None => quote! { sum },
+    }
+}
+
+/// Sum up the products into a local variable `sum` over the flattened contraction indices
+/// for [Options::deterministic] or [Summation::Pairwise], and store it into the output element
+fn local_sum_tt(
+    options: &Options,
+    inner_mul: &TokenStream2,
+    output_elem: &TokenStream2,
+    contraction_indices: &[char],
+    parallel_chunks: Option<usize>,
+) -> TokenStream2 {
+    let local = local_type(options);
+    let store = store_tt(options);
+    let sum = if options.deterministic {
+        let (decode, total) = flatten(contraction_indices);
+        let chunk = DETERMINISTIC_CHUNK;
+        let chunk_sum = flat_sum_tt(
+            options,
+            &local,
+            &decode,
+            inner_mul,
+            quote! { begin },
+            quote! { end },
+        );
+        let chunk_sum = quote! {
+            |chunk: usize| {
+                let begin = chunk * #chunk;
+                let end = (begin + #chunk).min(n_terms);
+                #chunk_sum
+            }
+        };
+        let sequential = quote! {
+            tree_sum::<#local, _>(0, n_terms.div_ceil(#chunk), &#chunk_sum)
+        };
+        match parallel_chunks {
+            Some(threshold) => {
+                // Accumulation type is `Copy` as required by `num_traits::AsPrimitive`
+                let clone = match &options.accumulate {
+                    Some(_) => None,
+                    None => Some(quote! { .clone() }),
+                };
+                quote! {
+                    let n_terms = #total;
+                    let sum = if n_terms >= #threshold {
+                        use ndarray::parallel::prelude::*;
+                        let partial: alloc::vec::Vec<#local> = (0..n_terms.div_ceil(#chunk))
+                            .into_par_iter()
+                            .map(#chunk_sum)
+                            .collect();
+                        tree_sum::<#local, _>(0, partial.len(), &|chunk: usize| partial[chunk] #clone)
+                    } else {
+                        #sequential
+                    };
+                }
+            }
+            None => quote! {
+                let n_terms = #total;
+                let sum = #sequential;
+            },
+        }
+    } else {
+        let (decode, total) = flatten(contraction_indices);
+        let sum = flat_sum_tt(options, &local, &decode, inner_mul, quote! { 0 }, total);
+        quote! { let sum = #sum; }
+    };
+    quote! {
+        #sum
+        #output_elem = #store;
+    }
+}
+
+/// Definition of the local variables and the statement adding `term` to `sum`
+/// for [Summation::Sequential] and [Summation::Kahan]
+fn sum_step_tt(
+    options: &Options,
+    local: &TokenStream2,
+    term: &TokenStream2,
+) -> (TokenStream2, TokenStream2) {
+    // Accumulation type is `Copy` as required by `num_traits::AsPrimitive`
+    let clone = match &options.accumulate {
+        Some(_) => None,
+        None => Some(quote! { .clone() }),
+    };
+    let zero = zero_tt(local);
+    match options.summation {
+        Summation::Kahan => (
+            quote! {
+                let mut sum = #zero;
+                let mut compensation = #zero;
+            },
+            quote! {
+                let y = #term - compensation;
+                let t = sum #clone + y #clone;
+                compensation = (t #clone - sum) - y;
+                sum = t;
+            },
+        ),
+        _ => (
+            quote! {
+                let mut sum = #zero;
+            },
+            match &options.accumulate {
+                Some(_) => quote! { sum += #term; },
+                None => quote! { sum = sum + #term; },
+            },
+        ),
+    }
+}
+
+/// Decode the flattened index `l` into contraction indices, the last one runs fastest.
+/// Returns the decoding statements and the number of terms.
+fn flatten(contraction_indices: &[char]) -> (Vec<TokenStream2>, TokenStream2) {
+    let mut decode = Vec::new();
+    let mut sizes: Vec<syn::Ident> = Vec::new();
+    for &i in contraction_indices.iter().rev() {
+        let index = index_ident(i);
+        let n = n_ident(i);
+        decode.push(if sizes.is_empty() {
+            quote! { let #index = l % #n; }
+        } else {
+            let stride = product_of(&sizes);
+            quote! { let #index = l / (#stride) % #n; }
+        });
+        sizes.push(n);
+    }
+    (decode, product_of(&sizes))
+}
+
+/// Expression of the sum over the flattened index `l` in `begin..end`
+fn flat_sum_tt(
+    options: &Options,
+    local: &TokenStream2,
+    decode: &[TokenStream2],
+    term: &TokenStream2,
+    begin: TokenStream2,
+    end: TokenStream2,
+) -> TokenStream2 {
+    match options.summation {
+        Summation::Sequential | Summation::Kahan => {
+            let (init, add) = sum_step_tt(options, local, term);
+            quote! {
+                {
+                    #init
+                    for l in #begin..#end {
+                        #(#decode)*
+                        #add
+                    }
+                    sum
+                }
+            }
+        }
+        Summation::Pairwise => quote! {
+            pairwise_sum::<#local, _>(#begin, #end, &|l: usize| {
+                #(#decode)*
+                #term
+            })
+        },
+    }
+}
+
+/// `n_a * n_b * ...`
+pub(crate) fn product_of(sizes: &[syn::Ident]) -> TokenStream2 {
+    let (first, rest) = sizes.split_first().expect("sizes never be empty");
+    quote! { #first #(* #rest)* }
+}
+
+/// Zero of the type `ty` as the sum of no elements, so that the generated code only requires `core::iter::Sum`
+/// and users need not depend on `num-traits`
+///
+/// `Sum` does not require the sum of no elements to be zero,
+/// and the element types are assumed to return the additive identity for an empty iterator,
+/// as the primitive numbers do.
+pub(crate) fn zero_tt(ty: &TokenStream2) -> TokenStream2 {
+    quote! { <#ty as core::iter::Sum>::sum(core::iter::empty()) }
+}
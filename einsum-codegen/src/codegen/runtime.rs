@@ -0,0 +1,144 @@
+//! Calls into [einsum-runtime](https://crates.io/crates/einsum-runtime) crate for ndarray
+//!
+//! Instead of defining a function for each step, e.g. `ab,bc,cd->ad`, the steps are computed by
+//!
+//! ```ignore
+//! let out0 = einsum_runtime::contract(&[("ab", arg0.view().into_dyn()), ("bc", arg1.view().into_dyn())], "ac");
+//! let out1 = einsum_runtime::contract(&[("ac", out0.view()), ("cd", arg2.view().into_dyn())], "ad");
+//! ```
+//!
+//! which is generic over the subscripts and compiled once in einsum-runtime,
+//! so that the generated code is small regardless of the subscripts.
+
+use super::ndarray::{naive, ElemType};
+use crate::{Path, Subscript};
+use anyhow::{bail, Result};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+
+/// Indices of the subscript as a string literal, e.g. `"ab"`
+fn indices_literal(subscript: &Subscript) -> String {
+    subscript.indices().into_iter().collect()
+}
+
+/// Check that the options are supported by einsum-runtime
+///
+/// All operands have the same element type, and the products are summed up sequentially
+/// on the calling thread, so that [naive::Options::parallel] is not supported either.
+pub fn check_options(elem_type: &ElemType, options: &naive::Options) -> Result<()> {
+    if let ElemType::Explicit(_) = elem_type {
+        bail!("runtime option does not support the output element type");
+    }
+    let unsupported = [
+        ("accumulate", options.accumulate.is_some()),
+        (
+            "summation",
+            options.summation != naive::Summation::Sequential,
+        ),
+        ("deterministic", options.deterministic),
+        ("tile", options.tile.is_some()),
+        ("unchecked", options.unchecked),
+        ("parallel", options.parallel.is_some()),
+        ("from_dyn", options.from_dyn),
+        ("layout_dispatch", options.layout_dispatch),
+    ];
+    for (name, used) in unsupported {
+        if used {
+            bail!("runtime option cannot be combined with {} option", name);
+        }
+    }
+    Ok(())
+}
+
+/// Block computing einsum along the path by `einsum_runtime::contract`
+///
+/// The output is `ndarray::Array` of the rank of the output subscript, or a scalar for 0-rank output,
+/// or `ndarray::ArrayD` if `into_dyn` is true.
+pub fn einsum(path: &Path, args: &[syn::Expr], into_dyn: bool) -> TokenStream2 {
+    let arg_ident = &path.original().inputs;
+    let steps = path.iter().map(|ss| {
+        let operands = ss.inputs.iter().map(|arg| {
+            let indices = indices_literal(arg);
+            quote! { (#indices, #arg.view().into_dyn()) }
+        });
+        let out = &ss.output;
+        let indices = indices_literal(out);
+        quote! {
+            let #out = einsum_runtime::contract(&[#(#operands),*], #indices);
+        }
+    });
+    let output = path.output();
+    let rank = output.indices().len();
+    let out_tt = if into_dyn {
+        quote! { #output }
+    } else {
+        let dim = format_ident!("Ix{}", rank);
+        let scalar = (rank == 0).then(|| quote! { .into_scalar() });
+        quote! { #output.into_dimensionality::<ndarray::#dim>().unwrap() #scalar }
+    };
+    quote! {
+        {
+            #(let #arg_ident = #args;)*
+            #(#steps)*
+            #out_tt
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codegen::format_block;
+
+    #[test]
+    fn matmul3() {
+        let path = Path::brute_force("ij,jk,kl->il").unwrap();
+        let args = [
+            syn::parse_quote! { a },
+            syn::parse_quote! { b },
+            syn::parse_quote! { c },
+        ];
+        let tt = format_block(einsum(&path, &args, false).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            let arg0 = a;
+            let arg1 = b;
+            let arg2 = c;
+            let out1 = einsum_runtime::contract(
+                &[
+                    ("ab", arg0.view().into_dyn()),
+                    ("bc", arg1.view().into_dyn()),
+                ],
+                "ac",
+            );
+            let out0 = einsum_runtime::contract(
+                &[
+                    ("ab", out1.view().into_dyn()),
+                    ("bc", arg2.view().into_dyn()),
+                ],
+                "ac",
+            );
+            out0.into_dimensionality::<ndarray::Ix2>().unwrap()
+        }
+        "###);
+    }
+
+    #[test]
+    fn options() {
+        let options = naive::Options {
+            unchecked: true,
+            ..Default::default()
+        };
+        assert!(check_options(&ElemType::Product, &options).is_err());
+        let options = naive::Options {
+            parallel: Some(naive::PARALLEL_THRESHOLD),
+            ..Default::default()
+        };
+        assert!(check_options(&ElemType::Product, &options).is_err());
+        let options = naive::Options {
+            into_dyn: true,
+            ..Default::default()
+        };
+        assert!(check_options(&ElemType::Product, &options).is_ok());
+    }
+}
@@ -0,0 +1,466 @@
+//! For plain slices with explicit shapes and strides
+//!
+//! Each array is a tuple `(data, shape, strides)`, e.g. `(&[T], [usize; 2], [usize; 2])`,
+//! whose element at `(i, j)` is `data[i * strides[0] + j * strides[1]]`.
+//! The outputs are `(Vec<T>, [usize; N], [usize; N])` in the row-major order.
+//! With [Slice::out], the last step writes the output into the mutable slice in the row-major order
+//! without allocating `Vec<T>`.
+
+use super::{
+    print::{n_ident, strided_loops, zero_tt},
+    EinsumBackend,
+};
+use crate::{Path, Position, Subscript, Subscripts};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+
+/// [EinsumBackend] for slices
+#[derive(Clone, Default)]
+pub struct Slice {
+    /// Mutable slice where the output is written instead of returning `Vec<T>`
+    pub out: Option<syn::Expr>,
+}
+
+impl Slice {
+    /// Whether the step writes the output into [Slice::out]
+    fn writes_out(&self, subscripts: &Subscripts) -> bool {
+        self.out.is_some() && *subscripts.output.position() == Position::Out(0)
+    }
+}
+
+/// Product of the sizes, or `1` for no sizes
+fn product(sizes: &[TokenStream2]) -> TokenStream2 {
+    match sizes.split_first() {
+        Some((first, rest)) => quote! { #first #(* #rest)* },
+        None => quote! { 1 },
+    }
+}
+
+/// Strides of the row-major array of the shape, e.g. `[n_b * n_c, n_c, 1]` for `[n_a, n_b, n_c]`
+pub fn row_major_strides(shape: &[TokenStream2]) -> TokenStream2 {
+    let strides = (0..shape.len()).map(|k| product(&shape[k + 1..]));
+    quote! { [#(#strides),*] }
+}
+
+impl EinsumBackend for Slice {
+    /// The outputs are `alloc::vec::Vec<T>` except the last one written into [Slice::out]
+    fn uses_alloc(&self, path: &Path) -> bool {
+        self.out.is_none() || path.len() > 1
+    }
+
+    /// The output is the last argument `&mut [T]` without return value if [Slice::out] is given
+    fn signature(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = self.function_name(subscripts);
+        let args = &subscripts.inputs;
+        let rank = |arg: &Subscript| proc_macro2::Literal::usize_unsuffixed(arg.indices().len());
+        let ranks = args.iter().map(rank);
+        let output = &subscripts.output;
+        let out_rank = rank(output);
+        let (out_arg, out_ty) = if self.writes_out(subscripts) {
+            (Some(quote! { , #output: &mut [T] }), None)
+        } else {
+            (
+                None,
+                Some(quote! { -> (alloc::vec::Vec<T>, [usize; #out_rank], [usize; #out_rank]) }),
+            )
+        };
+        quote! {
+            fn #fn_name<T>(
+                #( #args: (&[T], [usize; #ranks], [usize; #ranks]) ),*
+                #out_arg
+            ) #out_ty
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>
+        }
+    }
+
+    /// [Subscripts::escaped_ident] with `_into` suffix if the step writes into [Slice::out]
+    fn function_name(&self, subscripts: &Subscripts) -> syn::Ident {
+        if self.writes_out(subscripts) {
+            format_ident!("{}_into", subscripts.escaped_ident())
+        } else {
+            format_ident!("{}", subscripts.escaped_ident())
+        }
+    }
+
+    /// The default composition, where the output is not returned if written into [Slice::out]
+    fn function_definition(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let signature = self.signature(subscripts);
+        let helpers = self.helpers(subscripts);
+        let array_size = self.array_size(subscripts);
+        let alloc = self.alloc(subscripts);
+        let kernel = self.kernel(subscripts);
+        let output = &subscripts.output;
+        let output = (!self.writes_out(subscripts)).then(|| quote! { #output });
+        quote! {
+            #signature {
+                #helpers
+                #array_size
+                #alloc
+                #kernel
+                #output
+            }
+        }
+    }
+
+    fn array_size(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let mut appeared: HashSet<char> = HashSet::new();
+        let mut tt = Vec::new();
+        for arg in subscripts.inputs.iter() {
+            if arg.indices().iter().all(|i| appeared.contains(i)) {
+                continue;
+            }
+            let n = arg.indices().into_iter().map(|i| {
+                if appeared.insert(i) {
+                    let n = n_ident(i);
+                    quote! { #n }
+                } else {
+                    quote! { _ }
+                }
+            });
+            tt.push(quote! { let [#(#n),*] = #arg.1; });
+        }
+        for arg in &subscripts.inputs {
+            // local variable, e.g. `n_1`
+            let n_each: Vec<_> = (0..arg.indices().len())
+                .map(|m| format_ident!("n_{}", m))
+                .collect();
+            // size of index defined previously, e.g. `n_i`
+            let n: Vec<_> = arg.indices().into_iter().map(n_ident).collect();
+            let message = format!(
+                "Slice is too short for the shape and strides of {}",
+                quote! { #arg }
+            );
+            // An empty array accesses no element, and otherwise the offset of the last element
+            // `sum((n - 1) * s)` must be in the slice, where the overflow fails the assert.
+            tt.push(quote! {
+                {
+                    let [#(#n_each),*] = #arg.1;
+                    #(assert_eq!(#n_each, #n);)*
+                }
+                assert!(
+                    #arg.1.contains(&0)
+                        || #arg
+                            .1
+                            .iter()
+                            .zip(#arg.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < #arg.0.len()),
+                    #message
+                );
+            });
+        }
+        quote! { #(#tt)* }
+    }
+
+    fn alloc(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let output = &subscripts.output;
+        let n: Vec<TokenStream2> = subscripts
+            .output
+            .indices()
+            .into_iter()
+            .map(|i| {
+                let n = n_ident(i);
+                quote! { #n }
+            })
+            .collect();
+        let len = product(&n);
+        let strides = row_major_strides(&n);
+        let zero = zero_tt(&quote! { T });
+        if self.writes_out(subscripts) {
+            let rank = proc_macro2::Literal::usize_unsuffixed(n.len());
+            return quote! {
+                assert_eq!(#output.len(), #len, "Output slice length mismatch");
+                #output.fill(#zero);
+                let #output: (&mut [T], [usize; #rank], [usize; #rank]) =
+                    (#output, [#(#n),*], #strides);
+            };
+        }
+        quote! {
+            let mut #output = (alloc::vec![#zero; #len], [#(#n),*], #strides);
+        }
+    }
+
+    fn index(&self, array: &TokenStream2, indices: &[syn::Ident], _mutable: bool) -> TokenStream2 {
+        if indices.is_empty() {
+            return quote! { #array.0[0] };
+        }
+        let offsets = indices.iter().enumerate().map(|(k, index)| {
+            let k = proc_macro2::Literal::usize_unsuffixed(k);
+            quote! { #index * #array.2[#k] }
+        });
+        quote! { #array.0[#(#offsets)+*] }
+    }
+
+    fn kernel(&self, subscripts: &Subscripts) -> TokenStream2 {
+        strided_loops(subscripts, self, false)
+    }
+
+    /// Pass the arrays as slices, e.g. `(&arg0.0[..], arg0.1, arg0.2)`, and [Slice::out] if given
+    fn function_call(&self, subscripts: &Subscripts) -> TokenStream2 {
+        let fn_name = self.function_name(subscripts);
+        let args = &subscripts.inputs;
+        let out = &subscripts.output;
+        match &self.out {
+            Some(out_expr) if self.writes_out(subscripts) => quote! {
+                let out: &mut [_] = #out_expr;
+                #fn_name(#((&#args.0[..], #args.1, #args.2),)* out);
+            },
+            _ => quote! {
+                let #out = #fn_name(#((&#args.0[..], #args.1, #args.2)),*);
+            },
+        }
+    }
+
+    /// `Vec<T>` for the output, or a scalar for 0-rank output,
+    /// or nothing if written into the mutable slice [Slice::out]
+    fn output(&self, output: &Subscript) -> TokenStream2 {
+        match &self.out {
+            Some(_) => TokenStream2::new(),
+            None if output.indices().is_empty() => quote! { #output.0[0].clone() },
+            None => quote! { #output.0 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{einsum, format_block},
+        *,
+    };
+
+    #[test]
+    fn matmul() {
+        let path = Path::brute_force("ij,jk->ik").unwrap();
+        let args = [syn::parse_quote! { a }, syn::parse_quote! { b }];
+        let tt = format_block(einsum(&Slice::default(), &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            extern crate alloc;
+            fn ab_bc__ac<T>(
+                arg0: (&[T], [usize; 2], [usize; 2]),
+                arg1: (&[T], [usize; 2], [usize; 2]),
+            ) -> (alloc::vec::Vec<T>, [usize; 2], [usize; 2])
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let [n_a, n_b] = arg0.1;
+                let [_, n_c] = arg1.1;
+                {
+                    let [n_0, n_1] = arg0.1;
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                assert!(
+                    arg0.1.contains(&0)
+                        || arg0
+                            .1
+                            .iter()
+                            .zip(arg0.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < arg0.0.len()),
+                    "Slice is too short for the shape and strides of arg0"
+                );
+                {
+                    let [n_0, n_1] = arg1.1;
+                    assert_eq!(n_0, n_b);
+                    assert_eq!(n_1, n_c);
+                }
+                assert!(
+                    arg1.1.contains(&0)
+                        || arg1
+                            .1
+                            .iter()
+                            .zip(arg1.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < arg1.0.len()),
+                    "Slice is too short for the shape and strides of arg1"
+                );
+                let mut out0 = (
+                    alloc :: vec ! [< T as core :: iter :: Sum > :: sum (core :: iter :: empty ()) ; n_a * n_c],
+                    [n_a, n_c],
+                    [n_c, 1],
+                );
+                for a in 0..n_a {
+                    for b in 0..n_b {
+                        let prod0 = arg0.0[a * arg0.2[0] + b * arg0.2[1]].clone();
+                        for c in 0..n_c {
+                            out0.0[a * out0.2[0] + c * out0.2[1]] =
+                                out0.0[a * out0.2[0] + c * out0.2[1]].clone()
+                                    + prod0.clone() * arg1.0[b * arg1.2[0] + c * arg1.2[1]].clone();
+                        }
+                    }
+                }
+                out0
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let out0 = ab_bc__ac((&arg0.0[..], arg0.1, arg0.2), (&arg1.0[..], arg1.1, arg1.2));
+            out0.0
+        }
+        "###);
+    }
+
+    #[test]
+    fn matmul3_out() {
+        // The intermediate is allocated, and the last step of the same subscripts writes into `d`
+        let path = Path::brute_force("ij,jk,kl->il").unwrap();
+        let args = [
+            syn::parse_quote! { a },
+            syn::parse_quote! { b },
+            syn::parse_quote! { c },
+        ];
+        let backend = Slice {
+            out: Some(syn::parse_quote! { d }),
+        };
+        let tt = format_block(einsum(&backend, &path, &args).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            extern crate alloc;
+            fn ab_bc__ac<T>(
+                arg0: (&[T], [usize; 2], [usize; 2]),
+                arg1: (&[T], [usize; 2], [usize; 2]),
+            ) -> (alloc::vec::Vec<T>, [usize; 2], [usize; 2])
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let [n_a, n_b] = arg0.1;
+                let [_, n_c] = arg1.1;
+                {
+                    let [n_0, n_1] = arg0.1;
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                assert!(
+                    arg0.1.contains(&0)
+                        || arg0
+                            .1
+                            .iter()
+                            .zip(arg0.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < arg0.0.len()),
+                    "Slice is too short for the shape and strides of arg0"
+                );
+                {
+                    let [n_0, n_1] = arg1.1;
+                    assert_eq!(n_0, n_b);
+                    assert_eq!(n_1, n_c);
+                }
+                assert!(
+                    arg1.1.contains(&0)
+                        || arg1
+                            .1
+                            .iter()
+                            .zip(arg1.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < arg1.0.len()),
+                    "Slice is too short for the shape and strides of arg1"
+                );
+                let mut out1 = (
+                    alloc :: vec ! [< T as core :: iter :: Sum > :: sum (core :: iter :: empty ()) ; n_a * n_c],
+                    [n_a, n_c],
+                    [n_c, 1],
+                );
+                for a in 0..n_a {
+                    for b in 0..n_b {
+                        let prod0 = arg0.0[a * arg0.2[0] + b * arg0.2[1]].clone();
+                        for c in 0..n_c {
+                            out1.0[a * out1.2[0] + c * out1.2[1]] =
+                                out1.0[a * out1.2[0] + c * out1.2[1]].clone()
+                                    + prod0.clone() * arg1.0[b * arg1.2[0] + c * arg1.2[1]].clone();
+                        }
+                    }
+                }
+                out1
+            }
+            fn ab_bc__ac_into<T>(
+                out1: (&[T], [usize; 2], [usize; 2]),
+                arg2: (&[T], [usize; 2], [usize; 2]),
+                out0: &mut [T],
+            ) where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T> + core::ops::Mul<Output = T>,
+            {
+                let [n_a, n_b] = out1.1;
+                let [_, n_c] = arg2.1;
+                {
+                    let [n_0, n_1] = out1.1;
+                    assert_eq!(n_0, n_a);
+                    assert_eq!(n_1, n_b);
+                }
+                assert!(
+                    out1.1.contains(&0)
+                        || out1
+                            .1
+                            .iter()
+                            .zip(out1.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < out1.0.len()),
+                    "Slice is too short for the shape and strides of out1"
+                );
+                {
+                    let [n_0, n_1] = arg2.1;
+                    assert_eq!(n_0, n_b);
+                    assert_eq!(n_1, n_c);
+                }
+                assert!(
+                    arg2.1.contains(&0)
+                        || arg2
+                            .1
+                            .iter()
+                            .zip(arg2.2)
+                            .try_fold(0_usize, |last, (n, s)| {
+                                n.checked_sub(1)?.checked_mul(s)?.checked_add(last)
+                            })
+                            .is_some_and(|last| last < arg2.0.len()),
+                    "Slice is too short for the shape and strides of arg2"
+                );
+                assert_eq!(out0.len(), n_a * n_c, "Output slice length mismatch");
+                out0.fill(<T as core::iter::Sum>::sum(core::iter::empty()));
+                let out0: (&mut [T], [usize; 2], [usize; 2]) = (out0, [n_a, n_c], [n_c, 1]);
+                for a in 0..n_a {
+                    for b in 0..n_b {
+                        let prod0 = out1.0[a * out1.2[0] + b * out1.2[1]].clone();
+                        for c in 0..n_c {
+                            out0.0[a * out0.2[0] + c * out0.2[1]] =
+                                out0.0[a * out0.2[0] + c * out0.2[1]].clone()
+                                    + prod0.clone() * arg2.0[b * arg2.2[0] + c * arg2.2[1]].clone();
+                        }
+                    }
+                }
+            }
+            let arg0 = a;
+            let arg1 = b;
+            let arg2 = c;
+            let out1 = ab_bc__ac((&arg0.0[..], arg0.1, arg0.2), (&arg1.0[..], arg1.1, arg1.2));
+            let out: &mut [_] = d;
+            ab_bc__ac_into(
+                (&out1.0[..], out1.1, out1.2),
+                (&arg2.0[..], arg2.1, arg2.2),
+                out,
+            );
+        }
+        "###);
+    }
+
+    #[test]
+    fn strides() {
+        let shape = [quote! { n_a }, quote! { n_b }, quote! { n_c }];
+        insta::assert_snapshot!(row_major_strides(&shape), @"[n_b * n_c , n_c , 1]");
+        insta::assert_snapshot!(row_major_strides(&[]), @"[]");
+    }
+}
@@ -0,0 +1,302 @@
+//! Loop-nest intermediate representation between [Subscripts] and generated code
+//!
+//! A [Kernel] for a single step of einsum is built by [Kernel::lower] as the perfect loop nest
+//!
+//! ```text
+//! for a in 0..n_a {
+//!     for c in 0..n_c {
+//!         for b in 0..n_b {
+//!             out[a, c] += in0[a, b] * in1[b, c];
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! for `ab,bc->ac`, where `in{k}` is the `k`-th input of the subscripts.
+//! Loop transformations, e.g. [Kernel::interchange] and [Kernel::tile], run on this nest,
+//! and each backend prints the result into tokens, e.g. [crate::codegen::ndarray::naive].
+//! The [fmt::Display] implementations print the IR in the above form for debugging.
+
+mod pass;
+
+use crate::Subscripts;
+use std::fmt;
+
+/// Array accessed in a [Kernel]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Array {
+    /// The `k`-th input of the subscripts
+    Input(usize),
+    /// The output of the subscripts
+    Output,
+}
+
+/// Element of an [Array] at the indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    pub array: Array,
+    pub indices: Vec<char>,
+}
+
+/// Value of the terms summed up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Element of an input converted into the type of the products
+    Load(Access),
+    /// Local variable bound by [Stmt::Let]
+    Var(String),
+    /// Product multiplied from left, i.e. `a * b * c` is `(a * b) * c`
+    Mul(Vec<Expr>),
+}
+
+impl Expr {
+    /// Factors of the product, or the expression itself if it is not a product
+    pub fn factors(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Mul(factors) => factors.iter().flat_map(|f| f.factors()).collect(),
+            _ => vec![self],
+        }
+    }
+}
+
+/// Range of [Loop::index]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    /// All of `0..n_i`
+    Full,
+    /// Loop over tiles of the given size in `0..n_i`, which binds `i_tile`
+    Tiles(usize),
+    /// Elements in the tile of the given size starting from `i_tile`
+    Tile(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub index: char,
+    pub range: Range,
+    /// Iterations run in parallel, see [Kernel::parallel]
+    pub parallel: bool,
+    pub body: Vec<Stmt>,
+}
+
+/// Where [Stmt::Accumulate] adds the terms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Element of the output
+    Elem(Access),
+    /// Local accumulator allocated by [Stmt::Accumulator]
+    Accumulator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    Loop(Loop),
+    /// Bind a local variable, e.g. a partial product hoisted out of loops
+    Let {
+        var: String,
+        value: Expr,
+    },
+    /// Allocate a local accumulator initialized by zero or by the element
+    Accumulator {
+        init: Option<Access>,
+    },
+    /// Add the terms to the target
+    Accumulate {
+        target: Target,
+        value: Expr,
+    },
+    /// Store the local accumulator into the element
+    Store(Access),
+    /// Sum up the terms over the flattened `indices` in a fixed order
+    /// into a local accumulator and store it into the element
+    Reduce {
+        indices: Vec<char>,
+        value: Expr,
+        target: Access,
+        /// Chunks of the flattened indices are summed up in parallel
+        parallel: bool,
+    },
+}
+
+/// Loop nest computing the output of a single step of einsum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kernel {
+    /// Indices of the output
+    pub output: Vec<char>,
+    pub body: Vec<Stmt>,
+}
+
+impl Kernel {
+    /// Lower subscripts into the loops over output indices, contraction indices in this order,
+    /// and the product of the inputs accumulated into the output element in the innermost loop
+    ///
+    /// The output array is allocated outside of the kernel by [crate::codegen::EinsumBackend::alloc],
+    /// since each backend creates it in its own container.
+    pub fn lower(subscripts: &Subscripts) -> Self {
+        let output = subscripts.output.indices();
+        let value = Expr::Mul(
+            subscripts
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(k, arg)| {
+                    Expr::Load(Access {
+                        array: Array::Input(k),
+                        indices: arg.indices(),
+                    })
+                })
+                .collect(),
+        );
+        let mut body = vec![Stmt::Accumulate {
+            target: Target::Elem(Access {
+                array: Array::Output,
+                indices: output.clone(),
+            }),
+            value,
+        }];
+        let mut indices = output.clone();
+        indices.extend(subscripts.contraction_indices());
+        for &index in indices.iter().rev() {
+            body = vec![Stmt::Loop(Loop {
+                index,
+                range: Range::Full,
+                parallel: false,
+                body,
+            })];
+        }
+        Kernel { output, body }
+    }
+}
+
+impl fmt::Display for Array {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Array::Input(k) => write!(f, "in{}", k),
+            Array::Output => write!(f, "out"),
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indices: Vec<String> = self.indices.iter().map(|i| i.to_string()).collect();
+        write!(f, "{}[{}]", self.array, indices.join(", "))
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Load(access) => write!(f, "{}", access),
+            Expr::Var(var) => write!(f, "{}", var),
+            Expr::Mul(factors) => {
+                let factors: Vec<String> = factors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", factors.join(" * "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Elem(access) => write!(f, "{}", access),
+            Target::Accumulator => write!(f, "sum"),
+        }
+    }
+}
+
+impl Stmt {
+    fn fmt_indent(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+        match self {
+            Stmt::Loop(l) => {
+                let i = l.index;
+                let parallel = if l.parallel { "parallel " } else { "" };
+                match l.range {
+                    Range::Full => writeln!(f, "{}{}for {} in 0..n_{} {{", indent, parallel, i, i)?,
+                    Range::Tiles(tile) => writeln!(
+                        f,
+                        "{}{}for {}_tile in (0..n_{}).step_by({}) {{",
+                        indent, parallel, i, i, tile
+                    )?,
+                    Range::Tile(tile) => writeln!(
+                        f,
+                        "{}{}for {} in {}_tile..({}_tile + {}).min(n_{}) {{",
+                        indent, parallel, i, i, i, tile, i
+                    )?,
+                }
+                for stmt in &l.body {
+                    stmt.fmt_indent(f, depth + 1)?;
+                }
+                writeln!(f, "{}}}", indent)
+            }
+            Stmt::Let { var, value } => writeln!(f, "{}let {} = {};", indent, var, value),
+            Stmt::Accumulator { init: Some(init) } => writeln!(f, "{}sum = {};", indent, init),
+            Stmt::Accumulator { init: None } => writeln!(f, "{}sum = 0;", indent),
+            Stmt::Accumulate { target, value } => {
+                writeln!(f, "{}{} += {};", indent, target, value)
+            }
+            Stmt::Store(access) => writeln!(f, "{}{} = sum;", indent, access),
+            Stmt::Reduce {
+                indices,
+                value,
+                target,
+                parallel,
+            } => {
+                let parallel = if *parallel { "parallel " } else { "" };
+                let indices: String = indices.iter().collect();
+                writeln!(
+                    f,
+                    "{}{} = {}reduce({}) {};",
+                    indent, target, parallel, indices, value
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indent(f, 0)
+    }
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stmt in &self.body {
+            stmt.fmt_indent(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn lower() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let kernel = Kernel::lower(&subscripts);
+        insta::assert_snapshot!(kernel, @r###"
+        for a in 0..n_a {
+            for c in 0..n_c {
+                for b in 0..n_b {
+                    out[a, c] += in0[a, b] * in1[b, c];
+                }
+            }
+        }
+        "###);
+
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "i,i->").unwrap();
+        let kernel = Kernel::lower(&subscripts);
+        insta::assert_snapshot!(kernel, @r###"
+        for a in 0..n_a {
+            out[] += in0[a] * in1[a];
+        }
+        "###);
+    }
+}
@@ -0,0 +1,369 @@
+//! Loop transformations on [Kernel]
+
+use super::*;
+
+/// Loops nested in a chain, i.e. the body of each loop has exactly one loop except the innermost
+struct Chain {
+    /// Loops from outer to inner whose bodies are taken
+    loops: Vec<Loop>,
+    /// Statements before and after `loops[d]` in the body of its parent
+    pre: Vec<Vec<Stmt>>,
+    post: Vec<Vec<Stmt>>,
+    /// Body of the innermost loop
+    inner: Vec<Stmt>,
+}
+
+impl Chain {
+    fn split(mut body: Vec<Stmt>) -> Self {
+        let mut chain = Chain {
+            loops: Vec::new(),
+            pre: Vec::new(),
+            post: Vec::new(),
+            inner: Vec::new(),
+        };
+        loop {
+            let loops: Vec<usize> = body
+                .iter()
+                .enumerate()
+                .filter(|(_, stmt)| matches!(stmt, Stmt::Loop(_)))
+                .map(|(p, _)| p)
+                .collect();
+            if loops.len() != 1 {
+                chain.inner = body;
+                return chain;
+            }
+            let post = body.split_off(loops[0] + 1);
+            let mut l = match body.pop() {
+                Some(Stmt::Loop(l)) => l,
+                _ => unreachable!(),
+            };
+            let inner = std::mem::take(&mut l.body);
+            chain.loops.push(l);
+            chain.pre.push(body);
+            chain.post.push(post);
+            body = inner;
+        }
+    }
+
+    fn join(self) -> Vec<Stmt> {
+        let mut body = self.inner;
+        for ((mut l, mut pre), post) in self.loops.into_iter().zip(self.pre).zip(self.post).rev() {
+            l.body = body;
+            pre.push(Stmt::Loop(l));
+            pre.extend(post);
+            body = pre;
+        }
+        body
+    }
+
+    /// Positions of the loops over elements, i.e. except [Range::Tiles]
+    fn elements(&self) -> Vec<usize> {
+        self.loops
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| !matches!(l.range, Range::Tiles(_)))
+            .map(|(p, _)| p)
+            .collect()
+    }
+
+    /// The outermost level where all `indices` are bound,
+    /// where the level `d` is the body of the `d`-th element loop.
+    /// Indices out of the chain are regarded as bound outside.
+    fn level(&self, indices: &[char]) -> usize {
+        let elements = self.elements();
+        indices
+            .iter()
+            .filter_map(|i| elements.iter().position(|&p| self.loops[p].index == *i))
+            .map(|d| d + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Place a statement just before the element loop of the `level`,
+    /// or at the beginning of the innermost body for the innermost level
+    fn insert_before(&mut self, level: usize, stmt: Stmt) {
+        match self.elements().get(level) {
+            Some(&p) => self.pre[p].push(stmt),
+            None => self.inner.insert(0, stmt),
+        }
+    }
+
+    /// Place a statement just after the element loop of the `level`,
+    /// or at the end of the innermost body for the innermost level
+    fn insert_after(&mut self, level: usize, stmt: Stmt) {
+        match self.elements().get(level) {
+            Some(&p) => self.post[p].insert(0, stmt),
+            None => self.inner.push(stmt),
+        }
+    }
+
+    /// The statement accumulating terms in the innermost body
+    fn accumulate(&mut self) -> Option<(&mut Target, &mut Expr)> {
+        self.inner.iter_mut().find_map(|stmt| match stmt {
+            Stmt::Accumulate { target, value } => Some((target, value)),
+            _ => None,
+        })
+    }
+}
+
+impl Kernel {
+    fn map_chain(&mut self, f: impl FnOnce(&mut Chain)) {
+        let mut chain = Chain::split(std::mem::take(&mut self.body));
+        f(&mut chain);
+        self.body = chain.join();
+    }
+
+    /// Reorder the loops of `order` indices into this order.
+    /// The loops of the other indices stay at the same positions.
+    pub fn interchange(&mut self, order: &[char]) {
+        self.map_chain(|chain| {
+            let positions: Vec<usize> = chain
+                .loops
+                .iter()
+                .enumerate()
+                .filter(|(_, l)| order.contains(&l.index) && !l.parallel)
+                .map(|(p, _)| p)
+                .collect();
+            let mut loops: Vec<Loop> = positions.iter().map(|&p| chain.loops[p].clone()).collect();
+            loops.sort_by_key(|l| order.iter().position(|&i| i == l.index));
+            for (p, l) in positions.into_iter().zip(loops) {
+                chain.loops[p] = l;
+            }
+        });
+    }
+
+    /// Run the outermost loop in parallel if it is the loop of the first output index,
+    /// or the reduction for 0-rank output
+    pub fn parallel(&mut self) {
+        match self.body.as_mut_slice() {
+            [Stmt::Loop(l)] if self.output.first() == Some(&l.index) => l.parallel = true,
+            [Stmt::Reduce { parallel, .. }] => *parallel = true,
+            _ => {}
+        }
+    }
+
+    /// Split the loops of `indices` into tiles of `tile` size
+    ///
+    /// The loops over tiles are placed outside of the other loops except the parallel loop
+    /// in the order of the loops.
+    pub fn tile(&mut self, indices: &[char], tile: usize) {
+        self.map_chain(|chain| {
+            let mut tiles = Vec::new();
+            for l in &mut chain.loops {
+                if indices.contains(&l.index) && l.range == Range::Full && !l.parallel {
+                    l.range = Range::Tile(tile);
+                    tiles.push(Loop {
+                        index: l.index,
+                        range: Range::Tiles(tile),
+                        parallel: false,
+                        body: Vec::new(),
+                    });
+                }
+            }
+            let at = chain.loops.iter().take_while(|l| l.parallel).count();
+            for (k, l) in tiles.into_iter().enumerate() {
+                chain.loops.insert(at + k, l);
+                chain.pre.insert(at + k, Vec::new());
+                chain.post.insert(at + k, Vec::new());
+            }
+        });
+    }
+
+    /// Bind the partial products invariant in the inner loops to local variables
+    /// before these loops, e.g. `let prod0 = in0[a, b];`
+    ///
+    /// The factors are multiplied from left in the same order, so that the result does not change.
+    /// The partial products are not hoisted out of the parallel loop.
+    pub fn hoist(&mut self) {
+        self.map_chain(|chain| {
+            let base = chain.loops.iter().take_while(|l| l.parallel).count();
+            let inner_level = chain.elements().len();
+            let factors: Vec<Expr> = match chain.accumulate() {
+                Some((_, value)) => value.factors().into_iter().cloned().collect(),
+                None => return,
+            };
+            let levels: Vec<usize> = factors
+                .iter()
+                .map(|factor| match factor {
+                    Expr::Load(access) => chain.level(&access.indices).max(base),
+                    _ => base,
+                })
+                .collect();
+
+            let mut level = 0;
+            let mut hoisted: Option<Expr> = None;
+            let mut inline = Vec::new();
+            for (k, factor) in factors.into_iter().enumerate() {
+                level = level.max(levels[k]);
+                if level == inner_level {
+                    inline.push(factor);
+                    continue;
+                }
+                let value = match hoisted {
+                    Some(p) => Expr::Mul(vec![p, factor]),
+                    None => factor,
+                };
+                // Bind the partial product before it varies in the inner loops
+                let next = levels.get(k + 1).cloned().unwrap_or(inner_level);
+                hoisted = Some(if next > level {
+                    let var = format!("prod{}", k);
+                    chain.insert_before(
+                        level,
+                        Stmt::Let {
+                            var: var.clone(),
+                            value,
+                        },
+                    );
+                    Expr::Var(var)
+                } else {
+                    value
+                });
+            }
+            let value = Expr::Mul(hoisted.into_iter().chain(inline).collect());
+            if let Some((_, v)) = chain.accumulate() {
+                *v = value;
+            }
+        });
+    }
+
+    /// Sum up the terms into a local accumulator in the loops of the indices not in the target,
+    /// and store it into the target element once after these loops
+    ///
+    /// The accumulator is initialized by the target element if `init` is true, otherwise by zero.
+    /// Nothing changes unless the loops of the target indices are outside of the others.
+    pub fn accumulate_locally(&mut self, init: bool) {
+        self.map_chain(|chain| {
+            let access = match chain.accumulate() {
+                Some((Target::Elem(access), _)) => access.clone(),
+                _ => return,
+            };
+            let elements = chain.elements();
+            let inside = |p: &usize| !access.indices.contains(&chain.loops[*p].index);
+            let level = elements.iter().position(inside).unwrap_or(elements.len());
+            if !elements[level..].iter().all(inside) {
+                return;
+            }
+            if let Some((target, _)) = chain.accumulate() {
+                *target = Target::Accumulator;
+            }
+            let init = if init { Some(access.clone()) } else { None };
+            chain.insert_before(level, Stmt::Accumulator { init });
+            chain.insert_after(level, Stmt::Store(access));
+        });
+    }
+
+    /// Replace the innermost loops of the indices not in the target by [Stmt::Reduce]
+    /// summing up over them in a fixed order
+    pub fn reduce(&mut self) {
+        self.map_chain(|chain| {
+            let (access, value) = match chain.inner.as_slice() {
+                [Stmt::Accumulate {
+                    target: Target::Elem(access),
+                    value,
+                }] => (access.clone(), value.clone()),
+                _ => return,
+            };
+            let inner = chain
+                .loops
+                .iter()
+                .rev()
+                .take_while(|l| !access.indices.contains(&l.index) && l.range == Range::Full)
+                .count();
+            if inner == 0 {
+                return;
+            }
+            let depth = chain.loops.len() - inner;
+            let indices = chain.loops[depth..].iter().map(|l| l.index).collect();
+            chain.loops.truncate(depth);
+            chain.pre.truncate(depth);
+            chain.post.truncate(depth);
+            chain.inner = vec![Stmt::Reduce {
+                indices,
+                value,
+                target: access,
+                parallel: false,
+            }];
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn interchange_tile_hoist() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let mut kernel = Kernel::lower(&subscripts);
+        kernel.interchange(&['a', 'b', 'c']);
+        kernel.tile(&['a', 'b', 'c'], 16);
+        kernel.hoist();
+        insta::assert_snapshot!(kernel, @r###"
+        for a_tile in (0..n_a).step_by(16) {
+            for b_tile in (0..n_b).step_by(16) {
+                for c_tile in (0..n_c).step_by(16) {
+                    for a in a_tile..(a_tile + 16).min(n_a) {
+                        for b in b_tile..(b_tile + 16).min(n_b) {
+                            let prod0 = in0[a, b];
+                            for c in c_tile..(c_tile + 16).min(n_c) {
+                                out[a, c] += prod0 * in1[b, c];
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        "###);
+    }
+
+    #[test]
+    fn accumulate_locally() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let mut kernel = Kernel::lower(&subscripts);
+        kernel.accumulate_locally(true);
+        insta::assert_snapshot!(kernel, @r###"
+        for a in 0..n_a {
+            for c in 0..n_c {
+                sum = out[a, c];
+                for b in 0..n_b {
+                    sum += in0[a, b] * in1[b, c];
+                }
+                out[a, c] = sum;
+            }
+        }
+        "###);
+
+        // The contraction loop is not innermost
+        let mut kernel = Kernel::lower(&subscripts);
+        kernel.interchange(&['a', 'b', 'c']);
+        let before = kernel.clone();
+        kernel.accumulate_locally(true);
+        assert_eq!(kernel, before);
+    }
+
+    #[test]
+    fn parallel_reduce() {
+        let mut namespace = Namespace::init();
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "ij,jk->ik").unwrap();
+        let mut kernel = Kernel::lower(&subscripts);
+        kernel.reduce();
+        kernel.parallel();
+        kernel.interchange(&['c']);
+        insta::assert_snapshot!(kernel, @r###"
+        parallel for a in 0..n_a {
+            for c in 0..n_c {
+                out[a, c] = reduce(b) in0[a, b] * in1[b, c];
+            }
+        }
+        "###);
+
+        let subscripts = Subscripts::from_raw_indices(&mut namespace, "i,i->").unwrap();
+        let mut kernel = Kernel::lower(&subscripts);
+        kernel.reduce();
+        kernel.parallel();
+        insta::assert_snapshot!(kernel, @"out[] = parallel reduce(a) in0[a] * in1[a];");
+    }
+}
@@ -156,13 +156,17 @@
 //! and the objective of this crate is to (heuristically) solve this problem.
 //!
 
+pub mod build;
 pub mod codegen;
+pub mod ir;
 pub mod parser;
 
 mod namespace;
 mod path;
+mod path_info;
 mod subscripts;
 
 pub use namespace::*;
 pub use path::*;
+pub use path_info::*;
 pub use subscripts::*;
@@ -0,0 +1,247 @@
+//! Show what `einsum!` does for given subscripts without `cargo expand`
+//!
+//! ```text
+//! $ einsum-codegen "ij,jk,kl->il" i=10 j=20 k=30 l=40
+//! ```
+//!
+//! prints the parsed subscripts, the steps of the path with their costs,
+//! and the code generated for ndarray formatted by `rustfmt`.
+//! The costs are shown in orders of the index size `N` if the index sizes are not given.
+
+use anyhow::{bail, Context, Result};
+use einsum_codegen::{
+    codegen::{
+        self, format_block,
+        ndarray::{ElemType, Ndarray},
+    },
+    parser::{RawSubscript, RawSubscripts},
+    Path, Position, Subscript,
+};
+use std::{collections::BTreeMap, env, fmt::Write, str::FromStr};
+
+const USAGE: &str = "Usage: einsum-codegen <SUBSCRIPTS> [<INDEX>=<SIZE>]...
+
+Examples:
+  einsum-codegen \"ij,jk,kl->il\"
+  einsum-codegen \"ij,jk,kl->il\" i=10 j=20 k=30 l=40";
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (subscripts, sizes) = match args.split_first() {
+        Some((subscripts, sizes)) if subscripts != "-h" && subscripts != "--help" => {
+            (subscripts, sizes)
+        }
+        _ => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+    };
+    let sizes = parse_sizes(sizes)?;
+    println!("{}", path_report(subscripts, &sizes)?);
+    println!("{}", generated_code(subscripts)?);
+    Ok(())
+}
+
+/// Parse the index sizes, e.g. `i=10`
+fn parse_sizes(args: &[String]) -> Result<BTreeMap<char, usize>> {
+    let mut sizes = BTreeMap::new();
+    for arg in args {
+        let (index, size) = arg.split_once('=').with_context(|| {
+            format!("Index size must be `<INDEX>=<SIZE>`, e.g. `i=10`: {}", arg)
+        })?;
+        let mut chars = index.chars();
+        let index = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_lowercase() => c,
+            _ => bail!("Index must be a lowercase ASCII character: {}", index),
+        };
+        let size = size
+            .parse()
+            .with_context(|| format!("Invalid size of index `{}`: {}", index, size))?;
+        sizes.insert(index, size);
+    }
+    Ok(sizes)
+}
+
+/// Shapes of the arguments from the index sizes
+fn shapes(subscripts: &str, sizes: &BTreeMap<char, usize>) -> Result<Vec<Vec<usize>>> {
+    let raw = RawSubscripts::from_str(subscripts)?;
+    raw.inputs
+        .iter()
+        .map(|input| match input {
+            RawSubscript::Indices(indices) => indices
+                .iter()
+                .map(|c| {
+                    sizes
+                        .get(c)
+                        .cloned()
+                        .with_context(|| format!("Size of index `{}` is not given", c))
+                })
+                .collect(),
+            RawSubscript::Ellipsis { .. } => {
+                bail!("Index sizes cannot be given for subscripts with ellipsis")
+            }
+        })
+        .collect()
+}
+
+/// Steps of the path in the indices of the input, e.g. `ij,jk->ik | arg0,arg1->out1`,
+/// or `None` for subscripts with ellipsis whose indices are not named in the input
+///
+/// Each step of the path is written in its own indices from `a`, e.g. `ab,bc->ac | arg0,arg1->out1`,
+/// and they are mapped back through the indices of its operands.
+fn input_steps(subscripts: &str, path: &Path) -> Result<Option<Vec<String>>> {
+    let raw = RawSubscripts::from_str(subscripts)?;
+    let mut operands: BTreeMap<Position, Vec<char>> = BTreeMap::new();
+    for (k, input) in raw.inputs.iter().enumerate() {
+        match input {
+            RawSubscript::Indices(indices) => operands.insert(Position::Arg(k), indices.clone()),
+            RawSubscript::Ellipsis { .. } => return Ok(None),
+        };
+    }
+    let mut steps = Vec::new();
+    for ss in path.iter() {
+        let mut rename = BTreeMap::new();
+        for arg in &ss.inputs {
+            rename.extend(
+                arg.indices()
+                    .into_iter()
+                    .zip(operands[arg.position()].clone()),
+            );
+        }
+        let indices =
+            |arg: &Subscript| -> String { arg.indices().into_iter().map(|i| rename[&i]).collect() };
+        let inputs: Vec<String> = ss.inputs.iter().map(indices).collect();
+        let positions: Vec<String> = ss
+            .inputs
+            .iter()
+            .map(|arg| arg.position().to_string())
+            .collect();
+        let output = ss.output.position();
+        steps.push(format!(
+            "{}->{} | {}->{}",
+            inputs.join(","),
+            indices(&ss.output),
+            positions.join(","),
+            output
+        ));
+        operands.insert(*output, indices(&ss.output).chars().collect());
+    }
+    Ok(Some(steps))
+}
+
+/// The parsed subscripts and the steps of the path with their costs
+fn path_report(subscripts: &str, sizes: &BTreeMap<char, usize>) -> Result<String> {
+    let path = Path::brute_force(subscripts)?;
+    let steps = match input_steps(subscripts, &path)? {
+        Some(steps) => steps,
+        None => path.iter().map(|ss| ss.to_string()).collect(),
+    };
+    let costs: Vec<String> = if sizes.is_empty() {
+        path.iter()
+            .map(|ss| {
+                format!(
+                    "O(N^{}) operations, O(N^{}) memory",
+                    ss.compute_order(),
+                    ss.memory_order()
+                )
+            })
+            .collect()
+    } else {
+        path.costs(&shapes(subscripts, sizes)?)?
+            .iter()
+            .map(|cost| format!("{} operations, {} elements", cost.operations, cost.memory))
+            .collect()
+    };
+    let width = steps.iter().map(|step| step.len()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    writeln!(out, "Subscripts: {} ({})", subscripts, path.original())?;
+    writeln!(
+        out,
+        "Path: {} step{}, O(N^{}) operations, O(N^{}) memory",
+        path.len(),
+        if path.len() == 1 { "" } else { "s" },
+        path.compute_order(),
+        path.memory_order()
+    )?;
+    for (step, cost) in steps.iter().zip(costs) {
+        writeln!(out, "  {:width$}  {}", step, cost, width = width)?;
+    }
+    Ok(out)
+}
+
+/// The code generated by `einsum!` for ndarray with default options, where the arguments are `arg0`, `arg1`, ...
+fn generated_code(subscripts: &str) -> Result<String> {
+    let path = Path::brute_force(subscripts)?;
+    let args = (0..path.num_args())
+        .map(|n| syn::parse_str(&format!("arg{}", n)))
+        .collect::<syn::Result<Vec<syn::Expr>>>()?;
+    let backend = Ndarray {
+        elem_type: ElemType::Product,
+        options: Default::default(),
+    };
+    let code = format_block(codegen::einsum(&backend, &path, &args).to_string());
+    if code.is_empty() {
+        bail!("Failed to format the generated code by rustfmt");
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sizes(args: &[&str]) -> Result<BTreeMap<char, usize>> {
+        parse_sizes(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn orders() {
+        let report = path_report("ij,jk,kl->il", &BTreeMap::new()).unwrap();
+        insta::assert_snapshot!(report, @r###"
+        Subscripts: ij,jk,kl->il (ab,bc,cd->ad | arg0,arg1,arg2->out0)
+        Path: 2 steps, O(N^3) operations, O(N^2) memory
+          ij,jk->ik | arg0,arg1->out1  O(N^3) operations, O(N^2) memory
+          ik,kl->il | out1,arg2->out0  O(N^3) operations, O(N^2) memory
+        "###);
+    }
+
+    #[test]
+    fn exact_costs() {
+        let sizes = sizes(&["i=10", "j=20", "k=30", "l=40"]).unwrap();
+        let report = path_report("ij,jk,kl->il", &sizes).unwrap();
+        insta::assert_snapshot!(report, @r###"
+        Subscripts: ij,jk,kl->il (ab,bc,cd->ad | arg0,arg1,arg2->out0)
+        Path: 2 steps, O(N^3) operations, O(N^2) memory
+          ij,jk->ik | arg0,arg1->out1  12000 operations, 300 elements
+          ik,kl->il | out1,arg2->out0  24000 operations, 400 elements
+        "###);
+    }
+
+    #[test]
+    fn ellipsis() {
+        let report = path_report("...ij,...jk->...ik", &BTreeMap::new()).unwrap();
+        insta::assert_snapshot!(report, @r###"
+        Subscripts: ...ij,...jk->...ik (___ab,___bc->___ac | arg0,arg1->out0)
+        Path: 1 step, O(N^3) operations, O(N^2) memory
+          ___ab,___bc->___ac | arg0,arg1->out0  O(N^3) operations, O(N^2) memory
+        "###);
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        assert!(sizes(&["i"]).is_err());
+        assert!(sizes(&["ij=10"]).is_err());
+        assert!(sizes(&["i=-1"]).is_err());
+        // Size of `l` is missing
+        let sizes = sizes(&["i=10", "j=20", "k=30"]).unwrap();
+        assert!(path_report("ij,jk,kl->il", &sizes).is_err());
+    }
+
+    #[test]
+    fn code() {
+        let code = generated_code("i,i->").unwrap();
+        assert!(code.contains("fn a_a__"));
+        assert!(code.contains("let out0 = a_a__(arg0, arg1);"));
+    }
+}
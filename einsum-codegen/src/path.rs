@@ -1,8 +1,19 @@
 //! Execution path
 
 use crate::*;
-use anyhow::Result;
-use std::collections::BTreeSet;
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Exact cost of a step of [Path] for the shapes of the arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepCost {
+    /// Number of floating point operations counted in the same way as
+    /// [opt_einsum](https://github.com/dgasmith/opt_einsum),
+    /// i.e. the product of all index sizes times the number of operations for each element
+    pub operations: usize,
+    /// Number of elements of the output of the step
+    pub memory: usize,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Path {
@@ -18,6 +29,11 @@ impl std::ops::Deref for Path {
 }
 
 impl Path {
+    /// Subscripts of the whole einsum before factorized into the steps
+    pub fn original(&self) -> &Subscripts {
+        &self.original
+    }
+
     pub fn output(&self) -> &Subscript {
         &self.original.output
     }
@@ -34,6 +50,68 @@ impl Path {
         memory_order(&self.reduced_subscripts)
     }
 
+    /// Exact costs of each step for the shapes of the arguments, e.g. `[[10, 20], [20, 30]]` for `ij,jk->ik`
+    ///
+    /// ```
+    /// use einsum_codegen::*;
+    ///
+    /// let path = Path::brute_force("ij,jk,kl->il").unwrap();
+    /// let costs = path.costs(&[vec![10, 20], vec![20, 30], vec![30, 40]]).unwrap();
+    /// // `ij,jk->ik` and `ik,kl->il`, each multiplication and addition counted as 1 operation
+    /// assert_eq!(costs[0], StepCost { operations: 2 * 10 * 20 * 30, memory: 10 * 30 });
+    /// assert_eq!(costs[1], StepCost { operations: 2 * 10 * 30 * 40, memory: 10 * 40 });
+    /// ```
+    pub fn costs(&self, shapes: &[Vec<usize>]) -> Result<Vec<StepCost>> {
+        if shapes.len() != self.num_args() {
+            bail!(
+                "{} shapes are given for {} arguments",
+                shapes.len(),
+                self.num_args()
+            );
+        }
+        let mut known: BTreeMap<Position, Vec<usize>> = BTreeMap::new();
+        for (arg, shape) in self.original.inputs.iter().zip(shapes) {
+            known.insert(*arg.position(), shape.clone());
+        }
+        self.iter()
+            .map(|ss| {
+                let mut sizes: BTreeMap<char, usize> = BTreeMap::new();
+                for input in &ss.inputs {
+                    if let parser::RawSubscript::Ellipsis { .. } = input.raw() {
+                        bail!("Costs of subscripts with ellipsis are not supported");
+                    }
+                    let shape = &known[input.position()];
+                    let indices = input.indices();
+                    if shape.len() != indices.len() {
+                        bail!(
+                            "{} has {} axes, but its subscript requires {}",
+                            input.position(),
+                            shape.len(),
+                            indices.len()
+                        );
+                    }
+                    for (c, &n) in indices.into_iter().zip(shape) {
+                        match sizes.insert(c, n) {
+                            Some(m) if m != n => {
+                                bail!("Sizes of an index mismatch: {} and {}", m, n)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                let output: Vec<usize> = ss.output.indices().iter().map(|c| sizes[c]).collect();
+                // As `flop_count` of opt_einsum, each term counts `max(1, #inputs - 1)` operations
+                // for the product of the inputs, and one more addition if some index is contracted
+                let contracted = sizes.len() > output.len();
+                let operations = sizes.values().product::<usize>()
+                    * (std::cmp::max(1, ss.inputs.len() - 1) + contracted as usize);
+                let memory = output.iter().product();
+                known.insert(*ss.output.position(), output);
+                Ok(StepCost { operations, memory })
+            })
+            .collect()
+    }
+
     pub fn brute_force(indices: &str) -> Result<Self> {
         let mut names = Namespace::init();
         let subscripts = Subscripts::from_raw_indices(&mut names, indices)?;
@@ -119,6 +197,49 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn costs() -> Result<()> {
+        let path = Path::brute_force("ab,bc,cd,d->a")?;
+        let costs = path.costs(&[vec![2, 3], vec![3, 4], vec![4, 5], vec![5]])?;
+        assert_eq!(
+            costs,
+            [
+                StepCost {
+                    operations: 2 * 4 * 5,
+                    memory: 4
+                },
+                StepCost {
+                    operations: 2 * 3 * 4,
+                    memory: 3
+                },
+                StepCost {
+                    operations: 2 * 2 * 3,
+                    memory: 2
+                },
+            ]
+        );
+        assert!(path
+            .costs(&[vec![2, 3], vec![4, 4], vec![4, 5], vec![5]])
+            .is_err());
+        assert!(path.costs(&[vec![2, 3]]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn costs_trace() -> Result<()> {
+        // A single operand without multiplication still counts `max(1, 0) = 1` operation for each term,
+        // and one more addition since `a` is contracted, i.e. 2 operations for each of 3 terms
+        let path = Path::brute_force("aa->")?;
+        assert_eq!(
+            path.costs(&[vec![3, 3]])?,
+            [StepCost {
+                operations: 2 * 3,
+                memory: 1
+            }]
+        );
+        Ok(())
+    }
+
     #[test]
     fn brute_force_a_a_a() -> Result<()> {
         let path = Path::brute_force("a,a,a->")?;
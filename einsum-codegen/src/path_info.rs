@@ -0,0 +1,356 @@
+//! Report of the contraction path in the format of
+//! [`opt_einsum.contract_path`](https://optimized-einsum.readthedocs.io/en/stable/path_finding.html)
+
+use crate::{parser::*, *};
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+/// A step of [PathInfo], shown as a row of the table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepInfo {
+    /// Number of the indices appearing in the step
+    pub scaling: usize,
+    /// BLAS routine which can compute the step, e.g. `GEMM`, or `None` if not
+    pub blas: Option<&'static str>,
+    /// The step in the indices of the original subscripts, e.g. `ij,jk->ik`
+    pub current: String,
+    /// The operands remaining after the step, e.g. `kl,ik->il`
+    pub remaining: String,
+}
+
+/// Costs of the contraction path for the index sizes compared to the naive contraction in a single step
+///
+/// This is displayed as `opt_einsum.contract_path` does:
+///
+/// ```
+/// use einsum_codegen::*;
+/// use maplit::btreemap;
+///
+/// let sizes = btreemap! { 'i' => 10, 'j' => 20, 'k' => 30, 'l' => 40 };
+/// let info = contract_path("ij,jk,kl->il", &sizes).unwrap();
+/// assert_eq!(info.optimized_cost, 2 * 10 * 20 * 30 + 2 * 10 * 30 * 40);
+/// println!("{}", info);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathInfo {
+    pub path: Path,
+    /// The complete contraction in explicit mode, e.g. `ij,jk,kl->il`
+    pub contraction: String,
+    /// Number of the indices
+    pub naive_scaling: usize,
+    /// Largest number of the indices appearing in a step
+    pub optimized_scaling: usize,
+    /// Number of floating point operations to compute the einsum in a single step
+    pub naive_cost: usize,
+    /// Number of floating point operations to compute the einsum along the path
+    pub optimized_cost: usize,
+    /// Number of the elements of the largest intermediate result including the output
+    pub largest_intermediate: usize,
+    pub steps: Vec<StepInfo>,
+}
+
+impl PathInfo {
+    pub fn speedup(&self) -> f64 {
+        self.naive_cost as f64 / self.optimized_cost as f64
+    }
+}
+
+/// Find the path of `subscripts` by [Path::brute_force] and summarize its costs for the index sizes
+///
+/// The floating point operations are counted as [Path::costs],
+/// and the sizes of all indices in `subscripts` are required.
+pub fn contract_path(subscripts: &str, sizes: &BTreeMap<char, usize>) -> Result<PathInfo> {
+    let raw = RawSubscripts::from_str(subscripts)?;
+    let path = Path::brute_force(subscripts)?;
+
+    // Indices of the original subscripts for each tensor
+    let mut labels: BTreeMap<Position, Vec<char>> = BTreeMap::new();
+    for (input, raw) in path.original().inputs.iter().zip(&raw.inputs) {
+        match raw {
+            RawSubscript::Indices(indices) => labels.insert(*input.position(), indices.clone()),
+            RawSubscript::Ellipsis { .. } => bail!("Subscripts with ellipsis are not supported"),
+        };
+    }
+    let size = |c: &char| {
+        sizes
+            .get(c)
+            .cloned()
+            .ok_or_else(|| anyhow!("Size of index `{}` is not given", c))
+    };
+    let shapes = path
+        .original()
+        .inputs
+        .iter()
+        .map(|input| labels[input.position()].iter().map(size).collect())
+        .collect::<Result<Vec<Vec<usize>>>>()?;
+    let costs = path.costs(&shapes)?;
+
+    let mut remaining: Vec<Position> = labels.keys().cloned().collect();
+    let mut steps = Vec::new();
+    for ss in path.iter() {
+        // Indices of the step are renamed from `a`, see [Subscripts]
+        let mut rename = BTreeMap::new();
+        for input in &ss.inputs {
+            for (c, label) in input.indices().into_iter().zip(&labels[input.position()]) {
+                rename.insert(c, *label);
+            }
+        }
+        let inputs: Vec<Vec<char>> = ss
+            .inputs
+            .iter()
+            .map(|input| labels[input.position()].clone())
+            .collect();
+        let output: Vec<char> = ss.output.indices().iter().map(|c| rename[c]).collect();
+        remaining.retain(|position| ss.inputs.iter().all(|input| input.position() != position));
+        remaining.push(*ss.output.position());
+        steps.push((
+            rename.len(),
+            blas(&inputs, &output),
+            format!("{}->{}", join(&inputs), String::from_iter(&output)),
+            remaining.clone(),
+        ));
+        labels.insert(*ss.output.position(), output);
+    }
+
+    let output = String::from_iter(&labels[path.output().position()]);
+    let inputs: Vec<Vec<char>> = path
+        .original()
+        .inputs
+        .iter()
+        .map(|input| labels[input.position()].clone())
+        .collect();
+    let indices: BTreeSet<char> = inputs.iter().flatten().cloned().collect();
+    let contracted = indices.len() > output.len();
+    let naive_cost = indices.iter().map(size).product::<Result<usize>>()?
+        * (std::cmp::max(1, inputs.len() - 1) + contracted as usize);
+
+    let steps: Vec<StepInfo> = steps
+        .into_iter()
+        .map(|(scaling, blas, current, remaining)| {
+            let remaining: Vec<Vec<char>> = remaining
+                .iter()
+                .map(|position| labels[position].clone())
+                .collect();
+            StepInfo {
+                scaling,
+                blas,
+                current,
+                remaining: format!("{}->{}", join(&remaining), output),
+            }
+        })
+        .collect();
+    Ok(PathInfo {
+        contraction: format!("{}->{}", join(&inputs), output),
+        naive_scaling: indices.len(),
+        optimized_scaling: steps.iter().map(|step| step.scaling).max().unwrap_or(0),
+        naive_cost,
+        optimized_cost: costs.iter().map(|cost| cost.operations).sum(),
+        largest_intermediate: costs.iter().map(|cost| cost.memory).max().unwrap_or(1),
+        steps,
+        path,
+    })
+}
+
+/// Join the indices of the operands, e.g. `ij,jk`
+fn join(operands: &[Vec<char>]) -> String {
+    operands
+        .iter()
+        .map(String::from_iter)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// BLAS routine which can compute the step in the same rule as `opt_einsum.helpers.can_blas`
+fn blas(inputs: &[Vec<char>], output: &[char]) -> Option<&'static str> {
+    let (left, right) = match inputs {
+        [left, right] => (left, right),
+        _ => return None,
+    };
+    for c in left.iter().chain(right) {
+        let nl = left.iter().filter(|&x| x == c).count();
+        let nr = right.iter().filter(|&x| x == c).count();
+        // Repeated indices in an operand, or appearing more than twice
+        if nl > 1 || nr > 1 || nl + nr > 2 {
+            return None;
+        }
+        // Implicit summation, e.g. `ab,bc->c`, or taking the diagonal, e.g. `ab,ca->ca`
+        if nl + nr - 1 == output.contains(c) as usize {
+            return None;
+        }
+    }
+    let removed: BTreeSet<char> = left
+        .iter()
+        .chain(right)
+        .filter(|c| !output.contains(c))
+        .cloned()
+        .collect();
+    if removed.is_empty() {
+        return Some("OUTER/EINSUM");
+    }
+    let rs = removed.len();
+    let head = |x: &[char]| x[..rs.min(x.len())].to_vec();
+    let tail = |x: &[char]| x[x.len().saturating_sub(rs)..].to_vec();
+    let left_set: BTreeSet<char> = left.iter().cloned().collect();
+    let right_set: BTreeSet<char> = right.iter().cloned().collect();
+    if left == right {
+        Some("DOT")
+    } else if left_set == right_set {
+        // DOT requires the transpose
+        None
+    } else if tail(left) == head(right)
+        || head(left) == tail(right)
+        || tail(left) == tail(right)
+        || head(left) == head(right)
+    {
+        Some("GEMM")
+    } else if left_set.is_subset(&removed) || right_set.is_subset(&removed) {
+        Some("GEMV/EINSUM")
+    } else {
+        Some("TDOT")
+    }
+}
+
+/// Format as Python's `{:.3e}`, e.g. `1.200e+04`
+fn scientific(value: f64) -> String {
+    let formatted = format!("{:.3e}", value);
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .expect("LowerExp always contains `e`");
+    let exponent: i32 = exponent.parse().expect("Exponent is an integer");
+    format!(
+        "{}e{}{:02}",
+        mantissa,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+impl fmt::Display for PathInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  Complete contraction:  {}", self.contraction)?;
+        writeln!(f, "         Naive scaling:  {}", self.naive_scaling)?;
+        writeln!(f, "     Optimized scaling:  {}", self.optimized_scaling)?;
+        writeln!(
+            f,
+            "      Naive FLOP count:  {}",
+            scientific(self.naive_cost as f64)
+        )?;
+        writeln!(
+            f,
+            "  Optimized FLOP count:  {}",
+            scientific(self.optimized_cost as f64)
+        )?;
+        writeln!(f, "   Theoretical speedup:  {}", scientific(self.speedup()))?;
+        writeln!(
+            f,
+            "  Largest intermediate:  {} elements",
+            scientific(self.largest_intermediate as f64)
+        )?;
+        writeln!(f, "{}", "-".repeat(80))?;
+        writeln!(
+            f,
+            "{:>6} {:>11} {:>22} {:>37}",
+            "scaling", "BLAS", "current", "remaining"
+        )?;
+        write!(f, "{}", "-".repeat(80))?;
+        for step in &self.steps {
+            let width = 56_usize.saturating_sub(std::cmp::max(22, step.current.len()));
+            write!(
+                f,
+                "\n{:>4} {:>14} {:>22}    {:>width$}",
+                step.scaling,
+                step.blas.unwrap_or("False"),
+                step.current,
+                step.remaining,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use maplit::btreemap;
+
+    fn chars(indices: &[&str]) -> Vec<Vec<char>> {
+        indices.iter().map(|x| x.chars().collect()).collect()
+    }
+
+    #[test]
+    fn matmul3() {
+        let sizes = btreemap! { 'i' => 10, 'j' => 20, 'k' => 30, 'l' => 40 };
+        let info = contract_path("ij,jk,kl->il", &sizes).unwrap();
+        insta::assert_snapshot!(info.to_string(), @r###"
+          Complete contraction:  ij,jk,kl->il
+                 Naive scaling:  4
+             Optimized scaling:  3
+              Naive FLOP count:  7.200e+05
+          Optimized FLOP count:  3.600e+04
+           Theoretical speedup:  2.000e+01
+          Largest intermediate:  4.000e+02 elements
+        --------------------------------------------------------------------------------
+        scaling        BLAS                current                             remaining
+        --------------------------------------------------------------------------------
+           3           GEMM              ij,jk->ik                             kl,ik->il
+           3           GEMM              ik,kl->il                                il->il
+        "###);
+    }
+
+    #[test]
+    fn implicit_output() {
+        let sizes = btreemap! { 'a' => 2, 'b' => 3, 'c' => 4, 'd' => 5 };
+        let info = contract_path("ab,bc,cd,d", &sizes).unwrap();
+        insta::assert_snapshot!(info.to_string(), @r###"
+          Complete contraction:  ab,bc,cd,d->a
+                 Naive scaling:  4
+             Optimized scaling:  2
+              Naive FLOP count:  4.800e+02
+          Optimized FLOP count:  7.600e+01
+           Theoretical speedup:  6.316e+00
+          Largest intermediate:  4.000e+00 elements
+        --------------------------------------------------------------------------------
+        scaling        BLAS                current                             remaining
+        --------------------------------------------------------------------------------
+           2           GEMM                cd,d->c                            ab,bc,c->a
+           2           GEMM                c,bc->b                               ab,b->a
+           2           GEMM                b,ab->a                                  a->a
+        "###);
+    }
+
+    #[test]
+    fn missing_size() {
+        let sizes = btreemap! { 'i' => 10, 'j' => 20 };
+        assert!(contract_path("ij,jk->ik", &sizes).is_err());
+    }
+
+    #[test]
+    fn can_blas() {
+        let out: Vec<char> = "ik".chars().collect();
+        assert_eq!(blas(&chars(&["ij", "jk"]), &out), Some("GEMM"));
+        assert_eq!(blas(&chars(&["ji", "kj"]), &out), Some("GEMM"));
+        assert_eq!(blas(&chars(&["i", "k"]), &out), Some("OUTER/EINSUM"));
+        assert_eq!(blas(&chars(&["ij", "ij"]), &[]), Some("DOT"));
+        assert_eq!(blas(&chars(&["ij", "ji"]), &[]), None);
+        assert_eq!(
+            blas(&chars(&["ijk", "j"]), &['i', 'k']),
+            Some("GEMV/EINSUM")
+        );
+        assert_eq!(blas(&chars(&["ijk", "jlk"]), &['i', 'l']), Some("TDOT"));
+        // Taking the diagonal
+        assert_eq!(blas(&chars(&["ii", "ij"]), &['j']), None);
+        assert_eq!(blas(&chars(&["ij", "jk", "kl"]), &['i', 'l']), None);
+    }
+
+    #[test]
+    fn python_format() {
+        assert_eq!(scientific(12000.0), "1.200e+04");
+        assert_eq!(scientific(1.0), "1.000e+00");
+        assert_eq!(scientific(0.00125), "1.250e-03");
+    }
+}
@@ -1,12 +1,14 @@
 #![doc = include_str!("../README.md")]
 
-use einsum_codegen::{codegen::ndarray::*, *};
+use einsum_codegen::{
+    codegen::{self, faer::*, fixed::*, nalgebra::*, ndarray::*, slice::*, EinsumBackend},
+    *,
+};
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
-use proc_macro_error::{abort_call_site, proc_macro_error};
-use quote::quote;
-use std::collections::BTreeSet;
-use syn::parse::Parser;
+use proc_macro2::{Spacing, TokenStream as TokenStream2, TokenTree};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error};
+use std::collections::BTreeMap;
+use syn::parse::{ParseStream, Parser};
 
 /// proc-macro based einsum
 #[proc_macro_error]
@@ -16,23 +18,90 @@ pub fn einsum(input: TokenStream) -> TokenStream {
 }
 
 fn einsum2(input: TokenStream2) -> TokenStream2 {
-    let (subscripts, args) = parse(input);
-    let arg_ident: Vec<_> = (0..args.len()).map(Position::Arg).collect();
+    let EinsumInput {
+        subscripts,
+        args,
+        elem_type,
+        options,
+    } = parse(input);
+    let path = Path::brute_force(&subscripts).expect("Failed to construct execution path");
+    if path.num_args() != args.len() {
+        abort_call_site!(
+            "Argument number mismatch: subscripts ({}), args ({})",
+            path.num_args(),
+            args.len()
+        )
+    }
+    if options.runtime {
+        if let Err(e) = codegen::runtime::check_options(&elem_type, &options) {
+            abort_call_site!("{}", e)
+        }
+        return codegen::runtime::einsum(&path, &args, options.into_dyn);
+    }
+    codegen::einsum(&Ndarray { elem_type, options }, &path, &args)
+}
+
+/// proc-macro based einsum for nalgebra matrices and vectors
+///
+/// The operands are `nalgebra::Matrix` of rank at most 2, e.g. `DMatrix`, `SMatrix`, `DVector`,
+/// or their views, which have the same element type.
+/// Neither the output element type nor options are supported.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_nalgebra(input: TokenStream) -> TokenStream {
+    einsum_backend(input.into(), &Nalgebra, "einsum_nalgebra!", Some(2)).into()
+}
+
+/// proc-macro based einsum for faer matrices and columns
+///
+/// The operands are `faer::Mat`, `faer::Col`, their views, or scalars, which have the same element type.
+/// Neither the output element type nor options are supported.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_faer(input: TokenStream) -> TokenStream {
+    einsum_backend(input.into(), &Faer, "einsum_faer!", Some(2)).into()
+}
+
+/// proc-macro based einsum for nested fixed-size arrays, e.g. `[[f64; 3]; 3]`
+///
+/// The sizes of indices are taken from the types, and mismatched sizes are compile errors.
+/// Since the sizes are unknown at expansion time, the loops are not unrolled by this macro,
+/// and unrolling is left to the optimizer after monomorphization.
+/// The outputs are fixed-size arrays allocated on the stack, or scalars for fully contracted subscripts.
+/// Neither the output element type nor options are supported.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_array(input: TokenStream) -> TokenStream {
+    einsum_backend(input.into(), &Fixed, "einsum_array!", None).into()
+}
+
+/// proc-macro based einsum for plain slices with explicit shapes
+///
+/// Each operand is `(data, [shape..])` for the row-major array,
+/// or `(data, [shape..], [strides..])` for the strided one,
+/// where `data[..]` is `[T]`, e.g. `Vec<T>` or `&[T]`.
+/// The output is `Vec<T>` in the row-major order, or a scalar for fully contracted subscripts.
+/// It is written into a mutable slice instead by `; out = c`, where the last step writes into `c` directly.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_slice(input: TokenStream) -> TokenStream {
+    einsum_slice2(input.into()).into()
+}
+
+fn einsum_slice2(input: TokenStream2) -> TokenStream2 {
+    let (input, options) = split_options(input);
+    let out = options.map(|options| match parse_slice_options.parse2(options) {
+        Ok(out) => out,
+        Err(e) => abort!(e.span(), "{}", e),
+    });
+    let (input, output_type) = split_output_type(input);
+    if output_type.is_some() {
+        abort_call_site!("einsum_slice! does not support the output element type")
+    }
+    let EinsumInput {
+        subscripts, args, ..
+    } = parse(input);
     let path = Path::brute_force(&subscripts).expect("Failed to construct execution path");
-    let mut defined = BTreeSet::new();
-    let fn_defs: Vec<_> = path
-        .iter()
-        .filter_map(|ss| {
-            if defined.contains(&ss.escaped_ident()) {
-                None
-            } else {
-                defined.insert(ss.escaped_ident());
-                let inner = naive::inner(ss);
-                Some(function_definition(ss, inner))
-            }
-        })
-        .collect();
-    let out = path.output();
     if path.num_args() != args.len() {
         abort_call_site!(
             "Argument number mismatch: subscripts ({}), args ({})",
@@ -40,18 +109,395 @@ fn einsum2(input: TokenStream2) -> TokenStream2 {
             args.len()
         )
     }
+    let args: Vec<syn::Expr> = args
+        .into_iter()
+        .zip(&path.original().inputs)
+        .map(|(arg, ss)| slice_operand(arg, ss.indices().len()))
+        .collect();
+    codegen::einsum(&Slice { out }, &path, &args)
+}
+
+/// Parse options of `einsum_slice!`, i.e. `out = c`
+fn parse_slice_options(input: ParseStream) -> syn::Result<syn::Expr> {
+    let key: syn::Ident = input.parse()?;
+    if key != "out" {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("Unknown option for einsum_slice!: {}", key),
+        ));
+    }
+    input.parse::<syn::Token![=]>()?;
+    input.parse()
+}
 
-    quote! {
+/// Operand `(data, shape)` or `(data, shape, strides)` into `(data, shape, strides)`,
+/// where the row-major strides are filled if not given
+fn slice_operand(arg: syn::Expr, rank: usize) -> syn::Expr {
+    let elems: Vec<syn::Expr> = match arg {
+        syn::Expr::Tuple(tuple) if matches!(tuple.elems.len(), 2 | 3) => {
+            tuple.elems.into_iter().collect()
+        }
+        arg => abort!(
+            arg,
+            "einsum_slice! operand must be `(data, [shape..])` or `(data, [shape..], [strides..])`"
+        ),
+    };
+    for elem in &elems[1..] {
+        if let syn::Expr::Array(array) = elem {
+            if array.elems.len() != rank {
+                abort!(
+                    array,
+                    "Shape and strides must have {} elements for the rank of the subscript",
+                    rank
+                );
+            }
+        }
+    }
+    let data = &elems[0];
+    let shape = &elems[1];
+    let strides = match elems.get(2) {
+        Some(strides) => quote::quote! { #strides },
+        None => {
+            let shape: Vec<TokenStream2> = (0..rank).map(|k| quote::quote! { shape[#k] }).collect();
+            row_major_strides(&shape)
+        }
+    };
+    syn::parse_quote! {
         {
-            #(#fn_defs)*
-            #(let #arg_ident = #args;)*
-            #(#path)*
-            #out
+            let shape: [usize; #rank] = #shape;
+            let strides: [usize; #rank] = #strides;
+            (#data, shape, strides)
+        }
+    }
+}
+
+/// Public function computing einsum, e.g. `einsum_fn!(pub fn bmm = "bij,bjk->bik")`
+///
+/// The function takes `ndarray::ArrayBase` of the same element type `T` as the arguments `arg0`, `arg1`, ...,
+/// and its doc comment shows the subscripts and the path following the doc comments given in the input.
+/// Options are placed after `;` as `einsum!`, except for `accumulate`.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_fn(input: TokenStream) -> TokenStream {
+    einsum_fn2(input.into()).into()
+}
+
+fn einsum_fn2(input: TokenStream2) -> TokenStream2 {
+    let (input, options) = split_options(input);
+    let options = options_or_default(options);
+    let (signature, subscripts) = match parse_einsum_fn.parse2(input) {
+        Ok(input) => input,
+        Err(e) => abort!(e.span(), "{}", e),
+    };
+    named_function(signature, &subscripts, &options)
+}
+
+/// Attribute version of `einsum_fn!`, e.g. `#[einsum_function("bij,bjk->bik")] pub fn bmm();`
+///
+/// The function is declared without arguments and body, which are generated as `einsum_fn!`.
+/// The empty parentheses are required since `pub fn bmm;` is rejected by the Rust parser before expansion.
+/// Options are placed after `;`, e.g. `#[einsum_function("ij,jk->ik"; unchecked)]`.
+/// This cannot be named `einsum` since attribute macros share the namespace with `einsum!`.
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn einsum_function(attr: TokenStream, item: TokenStream) -> TokenStream {
+    einsum_function2(attr.into(), item.into()).into()
+}
+
+fn einsum_function2(attr: TokenStream2, item: TokenStream2) -> TokenStream2 {
+    let (attr, options) = split_options(attr);
+    let options = options_or_default(options);
+    let subscripts: syn::LitStr = match syn::parse2(attr) {
+        Ok(subscripts) => subscripts,
+        Err(e) => abort!(
+            e.span(),
+            "einsum_function requires subscripts, e.g. \"ij,jk->ik\""
+        ),
+    };
+    let signature = match parse_fn_declaration.parse2(item) {
+        Ok(signature) => signature,
+        Err(e) => abort!(e.span(), "{}", e),
+    };
+    named_function(signature, &subscripts, &options)
+}
+
+/// Report of the contraction path as `opt_einsum.contract_path`, e.g. `einsum_path!("ij,jk->ik"; i = 10, j = 20, k = 30)`
+///
+/// It expands to a string literal of [PathInfo] for the index sizes placed after `;`,
+/// which are required for all indices of the subscripts.
+#[proc_macro_error]
+#[proc_macro]
+pub fn einsum_path(input: TokenStream) -> TokenStream {
+    einsum_path2(input.into()).into()
+}
+
+fn einsum_path2(input: TokenStream2) -> TokenStream2 {
+    let (input, sizes) = split_options(input);
+    let subscripts: syn::LitStr = match syn::parse2(input) {
+        Ok(subscripts) => subscripts,
+        Err(e) => abort!(
+            e.span(),
+            "einsum_path! requires subscripts, e.g. \"ij,jk->ik\""
+        ),
+    };
+    let sizes = match sizes.map(|sizes| parse_sizes.parse2(sizes)) {
+        Some(Ok(sizes)) => sizes,
+        Some(Err(e)) => abort!(e.span(), "{}", e),
+        None => BTreeMap::new(),
+    };
+    let info = match contract_path(&subscripts.value(), &sizes) {
+        Ok(info) => info,
+        Err(e) => abort!(subscripts, "{}", e),
+    };
+    let report = info.to_string();
+    quote::quote! { #report }
+}
+
+/// Parse index sizes of `einsum_path!`, e.g. `i = 10, j = 20`
+fn parse_sizes(input: ParseStream) -> syn::Result<BTreeMap<char, usize>> {
+    let mut sizes = BTreeMap::new();
+    while !input.is_empty() {
+        let key: syn::Ident = input.parse()?;
+        let name = key.to_string();
+        let mut chars = name.chars();
+        let index = match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_lowercase() => c,
+            _ => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("Index must be a lowercase ASCII character: {}", key),
+                ))
+            }
+        };
+        input.parse::<syn::Token![=]>()?;
+        let value: syn::LitInt = input.parse()?;
+        sizes.insert(index, value.base10_parse()?);
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
         }
     }
+    Ok(sizes)
+}
+
+/// Attributes, visibility and name of the function declared in `einsum_fn!` and `#[einsum_function]`
+struct FnSignature {
+    attrs: Vec<syn::Attribute>,
+    vis: syn::Visibility,
+    name: syn::Ident,
 }
 
-fn parse(input: TokenStream2) -> (String, Vec<syn::Expr>) {
+fn parse_fn_signature(input: ParseStream) -> syn::Result<FnSignature> {
+    let attrs = input.call(syn::Attribute::parse_outer)?;
+    let vis = input.parse()?;
+    input.parse::<syn::Token![fn]>()?;
+    let name = input.parse()?;
+    Ok(FnSignature { attrs, vis, name })
+}
+
+/// Input of `einsum_fn!`, e.g. `/// doc \n pub fn bmm = "bij,bjk->bik"`
+fn parse_einsum_fn(input: ParseStream) -> syn::Result<(FnSignature, syn::LitStr)> {
+    let signature = parse_fn_signature(input)?;
+    input.parse::<syn::Token![=]>()?;
+    let subscripts = input.parse()?;
+    Ok((signature, subscripts))
+}
+
+/// Item of `#[einsum_function]`, e.g. `pub fn bmm();`
+fn parse_fn_declaration(input: ParseStream) -> syn::Result<FnSignature> {
+    let signature = parse_fn_signature(input)?;
+    let content;
+    syn::parenthesized!(content in input);
+    if !content.is_empty() {
+        return Err(content.error("Arguments are generated from the subscripts"));
+    }
+    input.parse::<syn::Token![;]>()?;
+    Ok(signature)
+}
+
+fn named_function(
+    signature: FnSignature,
+    subscripts: &syn::LitStr,
+    options: &naive::Options,
+) -> TokenStream2 {
+    let FnSignature { attrs, vis, name } = signature;
+    let path = match Path::brute_force(&subscripts.value()) {
+        Ok(path) => path,
+        Err(e) => abort!(subscripts, "{}", e),
+    };
+    let function = match named::named_function(&vis, &name, &subscripts.value(), &path, options) {
+        Ok(function) => function,
+        Err(e) => abort_call_site!("{}", e),
+    };
+    // Separate the generated doc comment from the given one
+    let separator = attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("doc"))
+        .then(|| quote::quote! { #[doc = ""] });
+    quote::quote! {
+        #(#attrs)*
+        #separator
+        #function
+    }
+}
+
+/// einsum by the backend without options, whose arrays are limited to `max_rank` if given
+fn einsum_backend(
+    input: TokenStream2,
+    backend: &impl EinsumBackend,
+    name: &str,
+    max_rank: Option<usize>,
+) -> TokenStream2 {
+    let (input, options) = split_options(input);
+    if options.is_some() {
+        abort_call_site!("{} does not support options", name)
+    }
+    let (input, output_type) = split_output_type(input);
+    if output_type.is_some() {
+        abort_call_site!("{} does not support the output element type", name)
+    }
+    let EinsumInput {
+        subscripts, args, ..
+    } = parse(input);
+    let path = Path::brute_force(&subscripts).expect("Failed to construct execution path");
+    if path.num_args() != args.len() {
+        abort_call_site!(
+            "Argument number mismatch: subscripts ({}), args ({})",
+            path.num_args(),
+            args.len()
+        )
+    }
+    if let Some(max_rank) = max_rank {
+        if let Err(e) = codegen::check_rank(path.iter(), max_rank) {
+            abort_call_site!("{}", e)
+        }
+    }
+    codegen::einsum(backend, &path, &args)
+}
+
+/// Input of `einsum!`, e.g. `"ij,jk->ik", a, b -> f64; accumulate = f64`
+struct EinsumInput {
+    subscripts: String,
+    args: Vec<syn::Expr>,
+    elem_type: ElemType,
+    options: naive::Options,
+}
+
+/// Split at the top-level `;` separating the options
+fn split_options(input: TokenStream2) -> (TokenStream2, Option<TokenStream2>) {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    for (n, token) in tokens.iter().enumerate() {
+        if let TokenTree::Punct(semi) = token {
+            if semi.as_char() == ';' {
+                return (
+                    tokens[..n].iter().cloned().collect(),
+                    Some(tokens[n + 1..].iter().cloned().collect()),
+                );
+            }
+        }
+    }
+    (tokens.into_iter().collect(), None)
+}
+
+/// Parse options, e.g. `accumulate = f64, summation = kahan, deterministic, tile = 32`
+fn parse_options(input: ParseStream) -> syn::Result<naive::Options> {
+    let mut options = naive::Options::default();
+    while !input.is_empty() {
+        let key: syn::Ident = input.parse()?;
+        match key.to_string().as_str() {
+            "deterministic" => options.deterministic = true,
+            "unchecked" => options.unchecked = true,
+            "into_dyn" => options.into_dyn = true,
+            "from_dyn" => options.from_dyn = true,
+            "runtime" => options.runtime = true,
+            "layout_dispatch" => options.layout_dispatch = true,
+            "accumulate" => {
+                input.parse::<syn::Token![=]>()?;
+                options.accumulate = Some(input.parse()?);
+            }
+            "tile" => {
+                options.tile = Some(if input.peek(syn::Token![=]) {
+                    input.parse::<syn::Token![=]>()?;
+                    let value: syn::LitInt = input.parse()?;
+                    let tile = value.base10_parse()?;
+                    if tile == 0 {
+                        return Err(syn::Error::new(value.span(), "Tile size must be positive"));
+                    }
+                    naive::Tile::Fixed(tile)
+                } else {
+                    naive::Tile::Auto
+                });
+            }
+            "parallel" => {
+                if !cfg!(feature = "parallel") {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "parallel option requires `parallel` feature of einsum-derive",
+                    ));
+                }
+                options.parallel = Some(if input.peek(syn::Token![=]) {
+                    input.parse::<syn::Token![=]>()?;
+                    let value: syn::LitInt = input.parse()?;
+                    value.base10_parse()?
+                } else {
+                    naive::PARALLEL_THRESHOLD
+                });
+            }
+            "summation" => {
+                input.parse::<syn::Token![=]>()?;
+                let value: syn::Ident = input.parse()?;
+                options.summation = value
+                    .to_string()
+                    .parse()
+                    .map_err(|e| syn::Error::new(value.span(), e))?;
+            }
+            _ => {
+                return Err(syn::Error::new(
+                    key.span(),
+                    format!("Unknown option for einsum!: {}", key),
+                ))
+            }
+        }
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+        }
+    }
+    Ok(options)
+}
+
+/// Split at the top-level `->` specifying the output element type
+///
+/// This cannot be parsed as a part of the last argument expression
+/// since `syn::Expr` parser tries to read `-` as a binary operator.
+fn split_output_type(input: TokenStream2) -> (TokenStream2, Option<TokenStream2>) {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    for (n, pair) in tokens.windows(2).enumerate() {
+        if let (TokenTree::Punct(minus), TokenTree::Punct(gt)) = (&pair[0], &pair[1]) {
+            if minus.as_char() == '-' && minus.spacing() == Spacing::Joint && gt.as_char() == '>' {
+                return (
+                    tokens[..n].iter().cloned().collect(),
+                    Some(tokens[n + 2..].iter().cloned().collect()),
+                );
+            }
+        }
+    }
+    (tokens.into_iter().collect(), None)
+}
+
+/// Options after `;`, or the default ones if not given
+fn options_or_default(options: Option<TokenStream2>) -> naive::Options {
+    match options {
+        Some(options) => match parse_options.parse2(options) {
+            Ok(options) => options,
+            Err(e) => abort!(e.span(), "{}", e),
+        },
+        None => Default::default(),
+    }
+}
+
+fn parse(input: TokenStream2) -> EinsumInput {
+    let (input, options) = split_options(input);
+    let options = options_or_default(options);
+    let (input, output_type) = split_output_type(input);
     let parser = syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated;
     let args = parser.parse2(input).expect("Invalid input for einsum!");
     let mut iter = args.into_iter();
@@ -65,7 +511,18 @@ fn parse(input: TokenStream2) -> (String, Vec<syn::Expr>) {
         panic!("einsum! must start with subscript string literal")
     };
     let args = iter.collect::<Vec<_>>();
-    (subscripts, args)
+    let elem_type = match output_type {
+        Some(ty) => ElemType::Explicit(Box::new(
+            syn::parse2(ty).expect("einsum! requires a type after `->` for output element type"),
+        )),
+        None => ElemType::Product,
+    };
+    EinsumInput {
+        subscripts,
+        args,
+        elem_type,
+        options,
+    }
 }
 
 #[cfg(test)]
@@ -77,11 +534,75 @@ mod test {
     #[test]
     fn test_parse() {
         let input = TokenStream2::from_str(r#""ab,bc->ac", x, y"#).unwrap();
-        let (subscripts, exprs) = parse(input);
+        let EinsumInput {
+            subscripts, args, ..
+        } = parse(input);
         assert_eq!(subscripts, "ab,bc->ac");
-        assert_eq!(exprs.len(), 2);
-        assert_eq!(exprs[0], syn::parse_str::<syn::Expr>("x").unwrap());
-        assert_eq!(exprs[1], syn::parse_str::<syn::Expr>("y").unwrap());
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0], syn::parse_str::<syn::Expr>("x").unwrap());
+        assert_eq!(args[1], syn::parse_str::<syn::Expr>("y").unwrap());
+    }
+
+    #[test]
+    fn test_parse_output_type() {
+        let input = TokenStream2::from_str(r#""ab,bc->ac", x, y.t() -> f64"#).unwrap();
+        let EinsumInput {
+            subscripts,
+            args,
+            elem_type,
+            ..
+        } = parse(input);
+        assert_eq!(subscripts, "ab,bc->ac");
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1], syn::parse_str::<syn::Expr>("y.t()").unwrap());
+        match elem_type {
+            ElemType::Explicit(ty) => assert_eq!(*ty, syn::parse_str::<syn::Type>("f64").unwrap()),
+            ElemType::Product => panic!("Output type is not parsed"),
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let input = TokenStream2::from_str(
+            r#""i,i->", x, y -> f32; accumulate = f64, summation = pairwise, deterministic"#,
+        )
+        .unwrap();
+        let EinsumInput { args, options, .. } = parse(input);
+        assert_eq!(args.len(), 2);
+        assert_eq!(
+            options.accumulate,
+            Some(syn::parse_str::<syn::Type>("f64").unwrap())
+        );
+        assert_eq!(options.summation, naive::Summation::Pairwise);
+        assert!(options.deterministic);
+        assert_eq!(options.parallel, None);
+        assert!(!options.from_dyn);
+
+        let input = TokenStream2::from_str(r#""ij,jk->ik", a, b; from_dyn, into_dyn"#).unwrap();
+        let EinsumInput { options, .. } = parse(input);
+        assert!(options.from_dyn);
+        assert!(options.into_dyn);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parse_parallel() {
+        let input = TokenStream2::from_str(r#""ij,jk->ik", a, b; tile, parallel = 1024"#).unwrap();
+        let EinsumInput { options, .. } = parse(input);
+        assert_eq!(options.tile, Some(naive::Tile::Auto));
+        assert!(!options.unchecked);
+        assert_eq!(options.parallel, Some(1024));
+
+        let input = TokenStream2::from_str(r#""ij,jk->ik", a, b; parallel"#).unwrap();
+        let EinsumInput { options, .. } = parse(input);
+        assert_eq!(options.parallel, Some(naive::PARALLEL_THRESHOLD));
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[test]
+    fn test_parse_parallel_without_feature() {
+        let input = TokenStream2::from_str("tile, parallel").unwrap();
+        assert!(parse_options.parse2(input).is_err());
     }
 
     #[test]
@@ -90,14 +611,16 @@ mod test {
         let tt = format_block(einsum2(input).to_string());
         insta::assert_snapshot!(tt, @r###"
         {
-            fn ab_bc__ac<T, S0, S1>(
+            fn ab_bc__ac<T, T0, T1, S0, S1>(
                 arg0: ndarray::ArrayBase<S0, ndarray::Ix2>,
                 arg1: ndarray::ArrayBase<S1, ndarray::Ix2>,
             ) -> ndarray::Array<T, ndarray::Ix2>
             where
-                T: ndarray::LinalgScalar,
-                S0: ndarray::Data<Elem = T>,
-                S1: ndarray::Data<Elem = T>,
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+                T0: Clone + core::ops::Mul<T1, Output = T>,
+                T1: Clone,
+                S0: ndarray::Data<Elem = T0>,
+                S1: ndarray::Data<Elem = T1>,
             {
                 let (n_a, n_b) = arg0.dim();
                 let (_, n_c) = arg1.dim();
@@ -111,12 +634,20 @@ mod test {
                     assert_eq!(n_0, n_b);
                     assert_eq!(n_1, n_c);
                 }
-                let mut out0 = ndarray::Array::zeros((n_a, n_c));
+                let mut out0 = ndarray::Array::<T, _>::from_elem(
+                    (n_a, n_c),
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
                 for a in 0..n_a {
-                    for c in 0..n_c {
-                        for b in 0..n_b {
-                            out0[(a, c)] = arg0[(a, b)] * arg1[(b, c)];
-                        }
+                    for b in 0..n_b {
+                        let prod0 = arg0[(a, b)].clone();
+                        let lane0 = out0.slice_mut(ndarray::s![a, 0..n_c]);
+                        let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                        ndarray::Zip::from(lane0)
+                            .and(lane1)
+                            .for_each(|out_elem, elem1| {
+                                (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                            });
                     }
                 }
                 out0
@@ -135,14 +666,16 @@ mod test {
         let tt = format_block(einsum2(input).to_string());
         insta::assert_snapshot!(tt, @r###"
         {
-            fn ab_bc__ac<T, S0, S1>(
+            fn ab_bc__ac<T, T0, T1, S0, S1>(
                 arg0: ndarray::ArrayBase<S0, ndarray::Ix2>,
                 arg1: ndarray::ArrayBase<S1, ndarray::Ix2>,
             ) -> ndarray::Array<T, ndarray::Ix2>
             where
-                T: ndarray::LinalgScalar,
-                S0: ndarray::Data<Elem = T>,
-                S1: ndarray::Data<Elem = T>,
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+                T0: Clone + core::ops::Mul<T1, Output = T>,
+                T1: Clone,
+                S0: ndarray::Data<Elem = T0>,
+                S1: ndarray::Data<Elem = T1>,
             {
                 let (n_a, n_b) = arg0.dim();
                 let (_, n_c) = arg1.dim();
@@ -156,12 +689,20 @@ mod test {
                     assert_eq!(n_0, n_b);
                     assert_eq!(n_1, n_c);
                 }
-                let mut out1 = ndarray::Array::zeros((n_a, n_c));
+                let mut out1 = ndarray::Array::<T, _>::from_elem(
+                    (n_a, n_c),
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
                 for a in 0..n_a {
-                    for c in 0..n_c {
-                        for b in 0..n_b {
-                            out1[(a, c)] = arg0[(a, b)] * arg1[(b, c)];
-                        }
+                    for b in 0..n_b {
+                        let prod0 = arg0[(a, b)].clone();
+                        let lane0 = out1.slice_mut(ndarray::s![a, 0..n_c]);
+                        let lane1 = arg1.slice(ndarray::s![b, 0..n_c]);
+                        ndarray::Zip::from(lane0)
+                            .and(lane1)
+                            .for_each(|out_elem, elem1| {
+                                (*out_elem) = (*out_elem).clone() + prod0.clone() * elem1.clone();
+                            });
                     }
                 }
                 out1
@@ -175,4 +716,55 @@ mod test {
         }
         "###);
     }
+
+    #[test]
+    fn einsum_a_a() {
+        let input = TokenStream2::from_str(r#""a,a->", x, y"#).unwrap();
+        let tt = format_block(einsum2(input).to_string());
+        insta::assert_snapshot!(tt, @r###"
+        {
+            fn a_a__<T, T0, T1, S0, S1>(
+                arg0: ndarray::ArrayBase<S0, ndarray::Ix1>,
+                arg1: ndarray::ArrayBase<S1, ndarray::Ix1>,
+            ) -> ndarray::Array<T, ndarray::Ix0>
+            where
+                T: Clone + core::iter::Sum + core::ops::Add<Output = T>,
+                T0: Clone + core::ops::Mul<T1, Output = T>,
+                T1: Clone,
+                S0: ndarray::Data<Elem = T0>,
+                S1: ndarray::Data<Elem = T1>,
+            {
+                let n_a = arg0.dim();
+                let _ = arg1.dim();
+                {
+                    let n_0 = arg0.dim();
+                    assert_eq!(n_0, n_a);
+                }
+                {
+                    let n_0 = arg1.dim();
+                    assert_eq!(n_0, n_a);
+                }
+                let mut out0 = ndarray::Array::<T, _>::from_elem(
+                    (),
+                    <T as core::iter::Sum>::sum(core::iter::empty()),
+                );
+                let mut sum = out0[()].clone();
+                let lane0 = arg0.slice(ndarray::s![0..n_a]);
+                let lane1 = arg1.slice(ndarray::s![0..n_a]);
+                sum = ndarray::Zip::from(lane0)
+                    .and(lane1)
+                    .fold(sum, |mut sum, elem0, elem1| {
+                        sum = sum + elem0.clone() * elem1.clone();
+                        sum
+                    });
+                out0[()] = sum;
+                out0
+            }
+            let arg0 = x;
+            let arg1 = y;
+            let out0 = a_a__(arg0, arg1);
+            out0.into_scalar()
+        }
+        "###);
+    }
 }
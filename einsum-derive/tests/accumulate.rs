@@ -0,0 +1,37 @@
+//! Mixed-precision accumulation by `accumulate = T` option
+
+use einsum_derive::einsum;
+use ndarray::{array, Array1};
+
+#[test]
+fn f32_in_f64() {
+    // Small terms are lost when added to 1e8 in f32
+    let mut x = Array1::<f32>::ones(102);
+    x[0] = 1e8;
+    x[101] = -1e8;
+    let y = Array1::<f32>::ones(102);
+    assert_eq!(einsum!("i,i->", x.view(), y.view()), 0.0);
+    assert_eq!(einsum!("i,i->", x, y; accumulate = f64), 100.0);
+}
+
+#[test]
+fn i8_in_i32() {
+    // Partial sum 200 overflows i8, while the result fits
+    let x = array![100_i8, 100, -100];
+    let y = array![1_i8, 1, 1];
+    assert_eq!(einsum!("i,i->", x, y; accumulate = i32), 100);
+
+    let a = array![[100_i8, 100], [-100, 100]];
+    let b = array![[100_i8, 1], [100, 1]];
+    let c = einsum!("ij,jk->ik", a, b -> i32; accumulate = i32);
+    assert_eq!(c, array![[20000, 200], [0, 0]]);
+}
+
+#[test]
+fn path() {
+    let a = array![[1.0_f32, 2.0], [3.0, 4.0]];
+    let b = array![[1.0_f32, 2.0], [3.0, 4.0]];
+    let c = array![[1.0_f32, 0.0], [0.0, 1.0]];
+    let abc = einsum!("ij,jk,kl->il", a, b, c; accumulate = f64);
+    assert_eq!(abc, array![[7.0, 10.0], [15.0, 22.0]]);
+}
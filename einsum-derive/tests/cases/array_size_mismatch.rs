@@ -0,0 +1,7 @@
+use einsum_derive::einsum_array;
+
+fn main() {
+    let a = [[1.0, 2.0], [3.0, 4.0]];
+    let b = [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]];
+    let _c = einsum_array!("ij,jk->ik", a, b);
+}
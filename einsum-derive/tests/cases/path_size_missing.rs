@@ -0,0 +1,5 @@
+use einsum_derive::einsum_path;
+
+fn main() {
+    let _ = einsum_path!("ij,jk->ik"; i = 10, j = 20);
+}
@@ -0,0 +1,6 @@
+use einsum_derive::einsum_slice;
+
+fn main() {
+    let a = vec![1.0; 6];
+    let _c = einsum_slice!("ij,j->i", (&a, [2, 3]), (&a, [2, 3]));
+}
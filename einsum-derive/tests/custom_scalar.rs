@@ -0,0 +1,50 @@
+//! Element type which is neither `Copy` nor `ndarray::LinalgScalar`
+
+use einsum_derive::einsum;
+use ndarray::array;
+use std::{
+    iter::Sum,
+    ops::{Add, Mul},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Boxed(Box<i64>);
+
+impl Add for Boxed {
+    type Output = Boxed;
+    fn add(self, rhs: Boxed) -> Boxed {
+        Boxed(Box::new(*self.0 + *rhs.0))
+    }
+}
+
+impl Mul for Boxed {
+    type Output = Boxed;
+    fn mul(self, rhs: Boxed) -> Boxed {
+        Boxed(Box::new(*self.0 * *rhs.0))
+    }
+}
+
+// The sum of no elements is the zero of the generated code
+impl Sum for Boxed {
+    fn sum<I: Iterator<Item = Boxed>>(iter: I) -> Boxed {
+        iter.fold(Boxed(Box::new(0)), Add::add)
+    }
+}
+
+fn b(n: i64) -> Boxed {
+    Boxed(Box::new(n))
+}
+
+#[test]
+fn matmul() {
+    let a = array![[b(1), b(2)], [b(3), b(4)]];
+    let c = einsum!("ij,jk->ik", a.clone(), a);
+    assert_eq!(c, array![[b(7), b(10)], [b(15), b(22)]]);
+}
+
+#[test]
+fn dot() {
+    let x = array![b(1), b(2), b(3)];
+    let y = array![b(4), b(5), b(6)];
+    assert_eq!(einsum!("i,i->", x, y), b(32));
+}
@@ -0,0 +1,50 @@
+//! Fixed summation order by `deterministic` option
+
+use einsum_derive::einsum;
+use ndarray::Array1;
+
+/// Chunk size of deterministic summation, see `einsum_codegen::codegen::ndarray::naive::DETERMINISTIC_CHUNK`
+const CHUNK: usize = 1024;
+
+fn tree_sum(partial: &[f32]) -> f32 {
+    match partial.len() {
+        0 => 0.0,
+        1 => partial[0],
+        n => tree_sum(&partial[..n / 2]) + tree_sum(&partial[n / 2..]),
+    }
+}
+
+#[test]
+fn chunked_tree_order() {
+    let n = 10 * CHUNK + 123;
+    let x = Array1::from_shape_fn(n, |i| ((i * 7919) % 1000) as f32 * 1.1e-3);
+    let y = Array1::from_shape_fn(n, |i| ((i * 104729) % 997) as f32 * 0.7e-2);
+
+    let partial: Vec<f32> = x
+        .as_slice()
+        .unwrap()
+        .chunks(CHUNK)
+        .zip(y.as_slice().unwrap().chunks(CHUNK))
+        .map(|(x, y)| x.iter().zip(y).fold(0.0, |sum, (x, y)| sum + x * y))
+        .collect();
+    let expected = tree_sum(&partial);
+
+    let result = einsum!("i,i->", x.view(), y.view(); deterministic);
+    assert_eq!(result.to_bits(), expected.to_bits());
+}
+
+#[test]
+fn with_summation() {
+    let n = 3 * CHUNK;
+    let x = Array1::from_shape_fn(n, |i| (i % 17) as f64);
+    let y = Array1::from_shape_fn(n, |i| (i % 13) as f64);
+    let expected = einsum!("i,i->", x.view(), y.view());
+    assert_eq!(
+        einsum!("i,i->", x.view(), y.view(); deterministic, summation = kahan),
+        expected
+    );
+    assert_eq!(
+        einsum!("i,i->", x.view(), y.view(); deterministic, summation = pairwise),
+        expected
+    );
+}
@@ -0,0 +1,40 @@
+//! Dynamic-rank `ArrayD` operands checked at runtime with `from_dyn` option, and `into_dyn` option
+
+use einsum_derive::einsum;
+use ndarray::{array, ArrayD, IxDyn};
+
+#[test]
+fn dyn_inputs() {
+    let a = array![[1.0, 2.0], [3.0, 4.0]];
+    let b = array![[1.0, -1.0], [0.0, 2.0]];
+    let expected = einsum!("ij,jk->ik", a.view(), b.view());
+
+    let a_dyn: ArrayD<f64> = a.clone().into_dyn();
+    let b_dyn: ArrayD<f64> = b.clone().into_dyn();
+    let c = einsum!("ij,jk->ik", a_dyn.view(), b_dyn; from_dyn);
+    assert_eq!(c, expected);
+
+    // Mixed with fixed-rank operands
+    let x = ArrayD::from_shape_vec(IxDyn(&[2]), vec![1.0, 1.0]).unwrap();
+    assert_eq!(einsum!("ij,j->i", a, x; from_dyn), array![3.0, 7.0]);
+}
+
+#[test]
+fn into_dyn() {
+    let a = array![[1, 2], [3, 4]].into_dyn();
+    let c: ArrayD<i32> = einsum!("ij,jk->ik", a.view(), a.view(); from_dyn, into_dyn);
+    assert_eq!(c, array![[7, 10], [15, 22]].into_dyn());
+
+    // 0-rank array instead of a scalar
+    let trace: ArrayD<i32> = einsum!("ii->", a; from_dyn, into_dyn);
+    assert_eq!(trace.ndim(), 0);
+    assert_eq!(trace.into_iter().next(), Some(5));
+}
+
+#[test]
+#[should_panic(expected = "einsum: 2nd operand has 1 axes, but its subscript requires 2")]
+fn rank_mismatch() {
+    let a = array![[1.0, 2.0], [3.0, 4.0]];
+    let b = array![1.0, 2.0].into_dyn();
+    let _ = einsum!("ij,jk->ik", a, b; from_dyn);
+}
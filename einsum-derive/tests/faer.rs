@@ -0,0 +1,81 @@
+//! einsum_faer! for matrices and columns of faer
+
+use einsum_derive::einsum_faer;
+use faer::{Col, Mat};
+
+fn matmul(a: &Mat<f64>, b: &Mat<f64>) -> Mat<f64> {
+    let mut c = Mat::zeros(a.nrows(), b.ncols());
+    for i in 0..a.nrows() {
+        for l in 0..a.ncols() {
+            for j in 0..b.ncols() {
+                c[(i, j)] += a[(i, l)] * b[(l, j)];
+            }
+        }
+    }
+    c
+}
+
+fn assert_close(a: &Mat<f64>, b: &Mat<f64>) {
+    assert_eq!((a.nrows(), a.ncols()), (b.nrows(), b.ncols()));
+    for i in 0..a.nrows() {
+        for j in 0..a.ncols() {
+            assert!((a[(i, j)] - b[(i, j)]).abs() < 1e-10);
+        }
+    }
+}
+
+#[test]
+fn matmul_steps() {
+    let a = Mat::from_fn(4, 5, |i, j| (i as f64 + 0.5) / (j as f64 + 1.0));
+    let b = Mat::from_fn(5, 3, |i, j| (i * 3 + j) as f64 / 7.0);
+    let expected = matmul(&a, &b);
+    assert_close(&einsum_faer!("ij,jk->ik", &a, &b), &expected);
+    // Swapped operands given as views
+    assert_close(
+        &einsum_faer!("jk,ij->ik", b.as_ref(), a.as_ref()),
+        &expected,
+    );
+
+    // Transposed operands
+    let at = a.transpose().to_owned();
+    let bt = b.transpose().to_owned();
+    assert_close(&einsum_faer!("ji,kj->ik", &at, &bt), &expected);
+
+    // Three matrices along the path
+    let c = Mat::from_fn(3, 2, |i, j| (i + 2 * j) as f64);
+    assert_close(
+        &einsum_faer!("ij,jk,kl->il", &a, &b, &c),
+        &matmul(&expected, &c),
+    );
+}
+
+#[test]
+fn loops() {
+    let a = Mat::from_fn(4, 5, |i, j| (i * 5 + j) as f64);
+    let x = Col::from_fn(5, |i| i as f64 - 2.0);
+    let y: Col<f64> = einsum_faer!("ij,j->i", &a, &x);
+    for i in 0..4 {
+        let mut expected = 0.0;
+        for j in 0..5 {
+            expected += a[(i, j)] * x[j];
+        }
+        assert_eq!(y[i], expected);
+    }
+
+    let dot: f64 = einsum_faer!("i,i->", &x, &x);
+    assert_eq!(dot, 10.0);
+
+    let outer = einsum_faer!("i,j->ij", &x, &y);
+    assert_eq!(outer[(4, 3)], x[4] * y[3]);
+
+    let trace: f64 = einsum_faer!("ii->", Mat::<f64>::identity(3, 3));
+    assert_eq!(trace, 3.0);
+}
+
+#[test]
+#[should_panic]
+fn shape_mismatch() {
+    let a = Mat::<f64>::zeros(4, 5);
+    let b = Mat::<f64>::zeros(4, 3);
+    let _ = einsum_faer!("ij,jk->ik", &a, &b);
+}
@@ -0,0 +1,46 @@
+//! einsum_array! for nested fixed-size arrays
+
+use einsum_derive::einsum_array;
+
+#[test]
+fn matrices() {
+    let a = [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]];
+    let b = [[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+    let c: [[f64; 2]; 2] = einsum_array!("ij,jk->ik", a, b);
+    assert_eq!(c, [[4.0, 5.0], [10.0, 11.0]]);
+
+    // Rotation about z-axis applied twice
+    let r = [[0, -1, 0], [1, 0, 0], [0, 0, 1]];
+    let r2 = einsum_array!("ij,jk->ik", r, &r);
+    assert_eq!(r2, [[-1, 0, 0], [0, -1, 0], [0, 0, 1]]);
+    let x = [1, 2, 3];
+    assert_eq!(einsum_array!("ij,jk,k->i", r, r, x), [-1, -2, 3]);
+}
+
+#[test]
+fn vectors() {
+    let x = [1, 2, 3];
+    let y = [4, 5, 6];
+    assert_eq!(einsum_array!("i,i->", x, y), 32);
+    assert_eq!(
+        einsum_array!("i,j->ij", x, [1, -1]),
+        [[1, -1], [2, -2], [3, -3]]
+    );
+    let m = [[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+    assert_eq!(einsum_array!("ii->", m), 15);
+    assert_eq!(einsum_array!("ii->i", m), [1, 5, 9]);
+    assert_eq!(einsum_array!("ij->ji", [[1, 2, 3]]), [[1], [2], [3]]);
+}
+
+#[test]
+fn rank3() {
+    // Levi-Civita symbol contracted into the cross product
+    let mut eps = [[[0; 3]; 3]; 3];
+    for (i, j, k) in [(0, 1, 2), (1, 2, 0), (2, 0, 1)] {
+        eps[i][j][k] = 1;
+        eps[i][k][j] = -1;
+    }
+    let x = [1, 0, 0];
+    let y = [0, 1, 0];
+    assert_eq!(einsum_array!("ijk,j,k->i", eps, x, y), [0, 0, 1]);
+}
@@ -0,0 +1,61 @@
+//! Innermost loops over the lanes of contiguous and strided views
+
+use einsum_derive::einsum;
+use ndarray::{s, Array1, Array2};
+
+fn matmul(a: &Array2<f64>, b: &Array2<f64>) -> Array2<f64> {
+    let (m, k) = a.dim();
+    let n = b.dim().1;
+    let mut c = Array2::zeros((m, n));
+    for i in 0..m {
+        for l in 0..k {
+            for j in 0..n {
+                c[(i, j)] += a[(i, l)] * b[(l, j)];
+            }
+        }
+    }
+    c
+}
+
+#[test]
+fn contiguous_and_strided() {
+    let a = Array2::from_shape_fn((6, 10), |(i, j)| (i as f64 + 0.5) / (j as f64 + 1.0));
+    let b = Array2::from_shape_fn((10, 8), |(i, j)| (i * 3 + j) as f64 / 7.0);
+    let expected = matmul(&a, &b);
+    assert_eq!(einsum!("ij,jk->ik", a.view(), b.view()), expected);
+
+    // Every other column of a wider array is not contiguous
+    let wide = Array2::from_shape_fn(
+        (10, 16),
+        |(i, j)| {
+            if j % 2 == 0 {
+                b[(i, j / 2)]
+            } else {
+                f64::NAN
+            }
+        },
+    );
+    let strided = wide.slice(s![.., ..;2]);
+    assert_eq!(strided, b);
+    assert_eq!(einsum!("ij,jk->ik", a.view(), strided), expected);
+
+    // Contiguous with negative stride
+    let reversed = b.slice(s![.., ..;-1]).to_owned();
+    assert_eq!(
+        einsum!("ij,jk->ik", a.view(), reversed.slice(s![.., ..;-1])),
+        expected
+    );
+}
+
+#[test]
+fn dot_lanes() {
+    let x = Array1::from_shape_fn(37, |i| i as f64 / 3.0);
+    let y = Array1::from_shape_fn(37, |i| 1.0 / (i as f64 + 1.0));
+    let mut expected = 0.0;
+    for i in 0..37 {
+        expected += x[i] * y[i];
+    }
+    assert_eq!(einsum!("i,i->", x.view(), y.view()), expected);
+    let x2 = Array1::from_shape_fn(74, |i| x[i / 2]);
+    assert_eq!(einsum!("i,i->", x2.slice(s![..;2]), y.view()), expected);
+}
@@ -0,0 +1,107 @@
+//! Loop order for the layouts of inputs, dispatched at runtime with `layout_dispatch` option
+
+use einsum_derive::einsum;
+use ndarray::{s, Array2, Array3, ShapeBuilder};
+
+fn matmul(a: &Array2<i64>, b: &Array2<i64>) -> Array2<i64> {
+    let (m, k) = a.dim();
+    let n = b.dim().1;
+    Array2::from_shape_fn((m, n), |(i, j)| (0..k).map(|l| a[(i, l)] * b[(l, j)]).sum())
+}
+
+#[test]
+fn matmul_layouts() {
+    let a = Array2::from_shape_fn((5, 7), |(i, j)| (i * 7 + j) as i64 - 10);
+    let b = Array2::from_shape_fn((7, 3), |(i, j)| (i * 3 + j) as i64 % 5);
+    let expected = matmul(&a, &b);
+
+    // Same values in column-major layout
+    let a_f = Array2::from_shape_vec((5, 7).f(), a.t().iter().cloned().collect()).unwrap();
+    let b_f = Array2::from_shape_vec((7, 3).f(), b.t().iter().cloned().collect()).unwrap();
+    assert_eq!(a_f, a);
+    assert_eq!(b_f, b);
+
+    assert_eq!(einsum!("ij,jk->ik", a.view(), b.view()), expected);
+    assert_eq!(einsum!("ij,jk->ik", a_f.view(), b.view()), expected);
+    assert_eq!(einsum!("ij,jk->ik", a.view(), b_f.view()), expected);
+    assert_eq!(einsum!("ij,jk->ik", a_f.view(), b_f.view()), expected);
+
+    assert_eq!(
+        einsum!("ij,jk->ik", a.view(), b.view(); layout_dispatch),
+        expected
+    );
+    assert_eq!(
+        einsum!("ij,jk->ik", a_f.view(), b.view(); layout_dispatch),
+        expected
+    );
+    assert_eq!(
+        einsum!("ij,jk->ik", a.view(), b_f.view(); layout_dispatch),
+        expected
+    );
+    assert_eq!(
+        einsum!("ij,jk->ik", a_f.view(), b_f.view(); layout_dispatch),
+        expected
+    );
+}
+
+#[test]
+fn transposed_and_reversed() {
+    let a = Array2::from_shape_fn((7, 5), |(i, j)| (i * 5 + j) as i64 % 11);
+    let b = Array2::from_shape_fn((7, 3), |(i, j)| (i + 2 * j) as i64);
+    let expected = matmul(&a.t().to_owned(), &b);
+    assert_eq!(einsum!("ij,jk->ik", a.t(), b.view()), expected);
+
+    // Negative strides
+    let a_rev = a.slice(s![.., ..;-1]).to_owned();
+    let view = a_rev.slice(s![.., ..;-1]);
+    assert_eq!(einsum!("ij,jk->ik", view.t(), b.view()), expected);
+}
+
+#[test]
+fn three_inputs() {
+    let a = Array2::from_shape_fn((4, 5), |(i, j)| (i + j) as i64);
+    let b = Array2::from_shape_fn((5, 6), |(i, j)| (i * j) as i64 % 7);
+    let c = Array2::from_shape_fn((6, 3), |(i, j)| (i as i64) - (j as i64));
+    let expected = matmul(&matmul(&a, &b), &c);
+    let (a_t, b_t) = (a.t().to_owned(), b.t().to_owned());
+    assert_eq!(
+        einsum!("ij,jk,kl->il", a_t.t(), b_t.t(), c.view()),
+        expected
+    );
+    let c_t = c.t().to_owned();
+    assert_eq!(
+        einsum!("ij,jk,kl->il", a.view(), b.view(), c_t.t()),
+        expected
+    );
+    assert_eq!(
+        einsum!("ij,jk,kl->il", a_t.t(), b_t.t(), c.view(); layout_dispatch),
+        expected
+    );
+}
+
+#[test]
+fn rank3() {
+    let x = Array3::from_shape_fn((3, 4, 5), |(i, j, k)| (i * 20 + j * 5 + k) as i64);
+    let x_f = Array3::from_shape_vec((3, 4, 5).f(), {
+        let mut v = Vec::new();
+        for k in 0..5 {
+            for j in 0..4 {
+                for i in 0..3 {
+                    v.push(x[(i, j, k)]);
+                }
+            }
+        }
+        v
+    })
+    .unwrap();
+    assert_eq!(x_f, x);
+    let y = Array2::from_shape_fn((4, 5), |(j, k)| (j * k) as i64);
+    assert_eq!(
+        einsum!("ijk,jk->i", x.view(), y.view()),
+        einsum!("ijk,jk->i", x_f.view(), y.view())
+    );
+    assert_eq!(
+        einsum!("ijk,jk->i", x.view(), y.view()),
+        einsum!("ijk,jk->i", x_f.view(), y.view(); layout_dispatch)
+    );
+}
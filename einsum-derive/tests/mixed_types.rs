@@ -0,0 +1,44 @@
+//! Operands with different element types
+
+use einsum_derive::einsum;
+use ndarray::array;
+use num_complex::Complex;
+
+#[test]
+fn explicit_output_type() {
+    let w = array![[1.0_f32, 2.0], [3.0, 4.0]];
+    let x = array![[1.0_f64, 2.0], [3.0, 4.0]];
+    let c = einsum!("ij,jk->ik", w, x -> f64);
+    assert_eq!(c, array![[7.0, 10.0], [15.0, 22.0]]);
+}
+
+#[test]
+fn explicit_output_type_path() {
+    let a = array![[1_i32, 2], [3, 4]];
+    let b = array![[1_i64, 2], [3, 4]];
+    let c = array![[1_u8, 0], [0, 1]];
+    let abc = einsum!("ij,jk,kl->il", a, b, c -> i64);
+    assert_eq!(abc, array![[7, 10], [15, 22]]);
+}
+
+#[test]
+fn explicit_output_type_scalar() {
+    let x = array![1.0_f32, 2.0, 3.0];
+    let y = array![4.0_f64, 5.0, 6.0];
+    let dot = einsum!("i,i->", x, y -> f64);
+    assert_eq!(dot, 32.0);
+}
+
+#[test]
+fn product_output_type() {
+    let a = array![[Complex::new(1.0, 1.0), Complex::new(0.0, 1.0)]];
+    let b = array![[2.0], [3.0]];
+    let c = einsum!("ij,jk->ik", a, b);
+    assert_eq!(c, array![[Complex::new(2.0, 5.0)]]);
+
+    // real matrix multiplied from the left
+    let a = array![[2.0, 3.0]];
+    let b = array![[Complex::new(1.0, 1.0)], [Complex::new(0.0, 1.0)]];
+    let c = einsum!("ij,jk->ik", a, b);
+    assert_eq!(c, array![[Complex::new(2.0, 5.0)]]);
+}
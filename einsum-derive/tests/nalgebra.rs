@@ -0,0 +1,66 @@
+//! einsum_nalgebra! for matrices and vectors of nalgebra
+
+use einsum_derive::einsum_nalgebra;
+use nalgebra::{DMatrix, DVector, Matrix2x3, Matrix3x4, SMatrix, Vector3};
+
+fn matmul(a: &DMatrix<f64>, b: &DMatrix<f64>) -> DMatrix<f64> {
+    let mut c = DMatrix::zeros(a.nrows(), b.ncols());
+    for i in 0..a.nrows() {
+        for l in 0..a.ncols() {
+            for j in 0..b.ncols() {
+                c[(i, j)] += a[(i, l)] * b[(l, j)];
+            }
+        }
+    }
+    c
+}
+
+#[test]
+fn dmatrix() {
+    let a = DMatrix::from_fn(4, 5, |i, j| (i as f64 + 0.5) / (j as f64 + 1.0));
+    let b = DMatrix::from_fn(5, 3, |i, j| (i * 3 + j) as f64 / 7.0);
+    let expected = matmul(&a, &b);
+    assert!((einsum_nalgebra!("ij,jk->ik", &a, &b) - &expected).norm() < 1e-12);
+    // Swapped arguments are also computed by gemm
+    assert!((einsum_nalgebra!("jk,ij->ik", &b, &a) - &expected).norm() < 1e-12);
+    // a^T * b
+    let at = a.transpose();
+    assert!((einsum_nalgebra!("ji,jk->ik", &at, &b) - &expected).norm() < 1e-12);
+    // Three matrices along the path
+    let c = DMatrix::from_fn(3, 2, |i, j| (i + 2 * j) as f64);
+    let abc = matmul(&expected, &c);
+    assert!((einsum_nalgebra!("ij,jk,kl->il", &a, &b, &c) - abc).norm() < 1e-10);
+}
+
+#[test]
+fn fixed_size() {
+    let a = Matrix2x3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+    let b = Matrix3x4::from_fn(|i, j| (i + j) as f64);
+    let c: DMatrix<f64> = einsum_nalgebra!("ij,jk->ik", &a, &b);
+    assert_eq!(c, DMatrix::from_column_slice(2, 4, (a * b).as_slice()));
+
+    let x = Vector3::new(1.0, -1.0, 2.0);
+    let y: DVector<f64> = einsum_nalgebra!("ij,j->i", &a, &x);
+    assert_eq!(y.as_slice(), (a * x).as_slice());
+
+    let m = SMatrix::<i32, 2, 2>::new(1, 2, 3, 4);
+    let trace: i32 = einsum_nalgebra!("ii->", &m);
+    assert_eq!(trace, 5);
+}
+
+#[test]
+fn vectors() {
+    let x = DVector::from_fn(7, |i, _| i as f64);
+    let y = DVector::from_fn(7, |i, _| 1.0 / (i as f64 + 1.0));
+    assert_eq!(einsum_nalgebra!("i,i->", &x, &y), x.dot(&y));
+
+    // Outer product falls back to the loops
+    let outer = einsum_nalgebra!("i,j->ij", &x, &y);
+    assert_eq!(outer, &x * y.transpose());
+
+    // Transposed matrix-vector product with a view
+    let a = DMatrix::from_fn(7, 4, |i, j| (i * j) as f64);
+    let view = a.view((1, 0), (6, 4));
+    let z = DVector::from_fn(6, |i, _| i as f64 + 1.0);
+    assert_eq!(einsum_nalgebra!("ij,i->j", &view, &z), view.transpose() * z);
+}
@@ -0,0 +1,38 @@
+//! Named einsum functions by einsum_fn! and #[einsum_function]
+
+use einsum_derive::{einsum, einsum_fn, einsum_function};
+use ndarray::{array, Array};
+
+einsum_fn!(
+    /// Batched matrix multiplication
+    pub fn bmm = "bij,bjk->bik"
+);
+
+einsum_fn!(fn trace = "ii->"; summation = kahan);
+
+#[einsum_function("ij,jk,kl->il")]
+pub fn matmul3();
+
+#[einsum_function("ij,ij->"; unchecked)]
+fn frobenius();
+
+#[test]
+fn einsum_fn() {
+    let a = Array::from_shape_fn((2, 3, 4), |(b, i, j)| (b * 12 + i * 4 + j) as i64);
+    let b = Array::from_shape_fn((2, 4, 5), |(b, i, j)| (b + i * j) as i64 - 3);
+    let c = bmm(a.view(), b.view());
+    assert_eq!(c, einsum!("bij,bjk->bik", a.view(), b.view()));
+
+    let m = array![[1.0, 2.0], [3.0, 4.0]];
+    assert_eq!(trace(m.view()), 5.0);
+}
+
+#[test]
+fn attribute() {
+    let a = array![[1, 2], [3, 4]];
+    assert_eq!(
+        matmul3(a.view(), a.view(), a.view()),
+        array![[37, 54], [81, 118]]
+    );
+    assert_eq!(frobenius(a.view(), a.view()), 30);
+}
@@ -0,0 +1,93 @@
+//! Parallel output loops with `parallel` option
+#![cfg(feature = "parallel")]
+
+use einsum_derive::{einsum, einsum_fn};
+use ndarray::{Array1, Array2, Array3};
+
+einsum_fn!(fn matmul_parallel = "ij,jk->ik"; parallel);
+
+fn with_threads<R: Send>(n: usize, f: impl FnOnce() -> R + Send) -> R {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(n)
+        .build()
+        .unwrap()
+        .install(f)
+}
+
+#[test]
+fn matmul() {
+    let a = Array2::from_shape_fn((64, 48), |(i, j)| ((i * 31 + j * 17) % 23) as f64 * 0.1);
+    let b = Array2::from_shape_fn((48, 56), |(j, k)| ((j * 13 + k * 7) % 19) as f64 * 0.3);
+    let expected = Array2::from_shape_fn((64, 56), |(i, k)| {
+        (0..48).fold(0.0, |sum, j| sum + a[(i, j)] * b[(j, k)])
+    });
+
+    let c1 = with_threads(1, || einsum!("ij,jk->ik", a.view(), b.view(); parallel));
+    let c4 = with_threads(4, || einsum!("ij,jk->ik", a.view(), b.view(); parallel));
+    assert_eq!(c1, expected);
+    assert_eq!(
+        with_threads(4, || matmul_parallel(a.view(), b.view())),
+        expected
+    );
+    // Summation order of each element does not depend on the number of threads
+    assert_eq!(c1.mapv(f64::to_bits), c4.mapv(f64::to_bits));
+}
+
+#[test]
+fn below_threshold() {
+    let a = Array2::from_shape_fn((64, 48), |(i, j)| (i + j) as i64);
+    let b = Array2::from_shape_fn((48, 56), |(j, k)| (j * k) as i64);
+    let c = with_threads(
+        4,
+        || einsum!("ij,jk->ik", a.view(), b.view(); parallel = 1000000000),
+    );
+    let expected = Array2::from_shape_fn((64, 56), |(i, k)| {
+        (0..48).map(|j| a[(i, j)] * b[(j, k)]).sum()
+    });
+    assert_eq!(c, expected);
+}
+
+#[test]
+fn rank1_output() {
+    let x = Array3::from_shape_fn((128, 32, 32), |(i, j, k)| ((i + 3 * j + 5 * k) % 11) as f32);
+    let y = Array2::from_shape_fn((32, 32), |(j, k)| ((j * k) % 7) as f32);
+    let z1 = with_threads(
+        1,
+        || einsum!("ijk,jk->i", x.view(), y.view(); accumulate = f64, parallel),
+    );
+    let z4 = with_threads(
+        4,
+        || einsum!("ijk,jk->i", x.view(), y.view(); accumulate = f64, parallel),
+    );
+    assert_eq!(z1.mapv(f32::to_bits), z4.mapv(f32::to_bits));
+}
+
+#[test]
+fn deterministic_scalar() {
+    let n = 100 * 1024 + 7;
+    let x = Array1::from_shape_fn(n, |i| ((i * 7919) % 1000) as f32 * 1.1e-3);
+    let y = Array1::from_shape_fn(n, |i| ((i * 104729) % 997) as f32 * 0.7e-2);
+    let sequential = einsum!("i,i->", x.view(), y.view(); deterministic);
+    let s1 = with_threads(
+        1,
+        || einsum!("i,i->", x.view(), y.view(); deterministic, parallel),
+    );
+    let s4 = with_threads(
+        4,
+        || einsum!("i,i->", x.view(), y.view(); deterministic, parallel),
+    );
+    assert_eq!(sequential.to_bits(), s1.to_bits());
+    assert_eq!(sequential.to_bits(), s4.to_bits());
+}
+
+#[test]
+fn unchecked() {
+    let a = Array2::from_shape_fn((64, 48), |(i, j)| ((i * 31 + j * 17) % 23) as f64 * 0.1);
+    let b = Array2::from_shape_fn((48, 56), |(j, k)| ((j * 13 + k * 7) % 19) as f64 * 0.3);
+    let expected = einsum!("ij,jk->ik", a.view(), b.view());
+    let c = with_threads(
+        4,
+        || einsum!("ij,jk->ik", a.view(), b.view(); parallel, unchecked),
+    );
+    assert_eq!(c, expected);
+}
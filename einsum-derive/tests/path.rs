@@ -0,0 +1,25 @@
+//! Report of the contraction path by `einsum_path!`
+
+use einsum_derive::einsum_path;
+use std::collections::BTreeMap;
+
+const MATMUL3: &str = einsum_path!("ij,jk,kl->il"; i = 10, j = 20, k = 30, l = 40);
+
+#[test]
+fn matmul3() {
+    assert!(MATMUL3.starts_with("  Complete contraction:  ij,jk,kl->il\n"));
+    assert!(MATMUL3.contains("  Optimized FLOP count:  3.600e+04\n"));
+    assert!(MATMUL3.ends_with(
+        "   3           GEMM              ik,kl->il                                il->il"
+    ));
+}
+
+#[test]
+fn same_as_library() {
+    let sizes = BTreeMap::from([('a', 2), ('b', 3), ('c', 4), ('d', 5)]);
+    let info = einsum_codegen::contract_path("ab,bc,cd,d->a", &sizes).unwrap();
+    assert_eq!(
+        einsum_path!("ab,bc,cd,d->a"; a = 2, b = 3, c = 4, d = 5),
+        info.to_string()
+    );
+}
@@ -0,0 +1,46 @@
+//! `runtime` option calling einsum-runtime instead of generating the loops
+
+use einsum_derive::einsum;
+use ndarray::{array, Array, ArrayD};
+
+#[test]
+fn same_as_generated() {
+    // Integers in f64 to be summed up exactly in any order
+    let a = Array::from_shape_fn((3, 4), |(i, j)| (i * 4 + j) as f64);
+    let b = Array::from_shape_fn((4, 5), |(i, j)| (i + j) as f64 - 3.0);
+    let c = Array::from_shape_fn((5, 2), |(i, j)| (i + 2 * j) as f64);
+    let (a, b, c) = (a.view(), b.view(), c.view());
+    assert_eq!(
+        einsum!("ij,jk->ik", a, b; runtime),
+        einsum!("ij,jk->ik", a, b)
+    );
+    assert_eq!(
+        einsum!("ij,jk,kl->il", a, b, c; runtime),
+        einsum!("ij,jk,kl->il", a, b, c)
+    );
+
+    // Loops for integers and the other subscripts
+    let m = array![[1, 2, 3], [4, 5, 6], [7, 8, 9]];
+    let m = m.view();
+    assert_eq!(einsum!("ii->", m; runtime), 15);
+    assert_eq!(einsum!("ij,ij->", m, m; runtime), einsum!("ij,ij->", m, m));
+    assert_eq!(einsum!("ij->ji", m; runtime), m.t());
+    assert_eq!(
+        einsum!("i,j->ij", array![1, 2], array![3, 4]; runtime),
+        array![[3, 4], [6, 8]]
+    );
+}
+
+#[test]
+fn into_dyn() {
+    let a = array![[1.0_f32, 2.0], [3.0, 4.0]].into_dyn();
+    let c: ArrayD<f32> = einsum!("ij,jk->ik", a.view(), a.view(); runtime, into_dyn);
+    assert_eq!(c, array![[7.0, 10.0], [15.0, 22.0]].into_dyn());
+}
+
+#[test]
+#[should_panic(expected = "einsum: operand of subscript `bc` has 1 axes")]
+fn rank_mismatch() {
+    let a = array![[1.0, 2.0], [3.0, 4.0]];
+    let _ = einsum!("ij,jk->ik", a, array![1.0, 2.0]; runtime);
+}
@@ -0,0 +1,93 @@
+//! einsum_slice! for plain slices with explicit shapes
+
+use einsum_derive::einsum_slice;
+
+/// Row-major product of `m x k` and `k x n` matrices
+fn matmul(a: &[f64], b: &[f64], m: usize, k: usize, n: usize) -> Vec<f64> {
+    let mut c = vec![0.0; m * n];
+    for i in 0..m {
+        for l in 0..k {
+            for j in 0..n {
+                c[i * n + j] += a[i * k + l] * b[l * n + j];
+            }
+        }
+    }
+    c
+}
+
+#[test]
+fn row_major() {
+    let (m, k, n) = (3, 4, 5);
+    let a: Vec<f64> = (0..m * k).map(|i| i as f64 / 3.0).collect();
+    let b: Vec<f64> = (0..k * n).map(|i| 1.0 / (i as f64 + 1.0)).collect();
+    let expected = matmul(&a, &b, m, k, n);
+    let c = einsum_slice!("ij,jk->ik", (&a, [m, k]), (b.as_slice(), [k, n]));
+    assert_eq!(c, expected);
+
+    // Three matrices along the path
+    let d: Vec<f64> = (0..n * 2).map(|i| i as f64).collect();
+    let abd = einsum_slice!("ij,jk,kl->il", (&a, [m, k]), (&b, [k, n]), (&d, [n, 2]));
+    assert_eq!(abd, matmul(&expected, &d, m, n, 2));
+
+    // Fully contracted
+    let x = [1, 2, 3];
+    let y = vec![4, 5, 6];
+    assert_eq!(einsum_slice!("i,i->", (&x, [3]), (y, [3])), 32);
+}
+
+#[test]
+fn strided() {
+    let (m, k, n) = (3, 4, 5);
+    let a: Vec<f64> = (0..m * k).map(|i| i as f64).collect();
+    let b: Vec<f64> = (0..k * n).map(|i| i as f64 - 7.0).collect();
+    let expected = matmul(&a, &b, m, k, n);
+
+    // Column-major `a` as the transpose of the row-major `k x m` matrix
+    let mut at = vec![0.0; m * k];
+    for i in 0..m {
+        for l in 0..k {
+            at[l * m + i] = a[i * k + l];
+        }
+    }
+    let c = einsum_slice!("ij,jk->ik", (&at, [m, k], [1, m]), (&b, [k, n]));
+    assert_eq!(c, expected);
+
+    // Every other element of a wider buffer
+    let wide: Vec<f64> = b.iter().flat_map(|&x| [x, f64::NAN]).collect();
+    let c = einsum_slice!("ij,jk->ik", (&a, [m, k]), (&wide, [k, n], [2 * n, 2]));
+    assert_eq!(c, expected);
+}
+
+#[test]
+fn write_into() {
+    let a = [1, 2, 3, 4];
+    let mut c = [0; 4];
+    einsum_slice!("ij,jk->ik", (&a, [2, 2]), (&a, [2, 2]); out = &mut c);
+    assert_eq!(c, [7, 10, 15, 22]);
+
+    let mut trace = [0];
+    einsum_slice!("ii->", (&a, [2, 2]); out = &mut trace[..]);
+    assert_eq!(trace, [5]);
+}
+
+#[test]
+#[should_panic(expected = "Slice is too short")]
+fn too_short() {
+    let a = [1.0; 5];
+    let _ = einsum_slice!("ij,j->i", (&a, [2, 3]), (&a, [3]));
+}
+
+#[test]
+#[should_panic(expected = "Slice is too short")]
+fn stride_overflow() {
+    let a = [1.0; 4];
+    let _ = einsum_slice!("ij,j->i", (&a, [2, 2], [usize::MAX, 1]), (&a, [2]));
+}
+
+#[test]
+fn zero_length_axis() {
+    let a: [f64; 0] = [];
+    let x = [1.0; 3];
+    let c = einsum_slice!("ij,j->i", (&a, [0, 3]), (&x, [3]));
+    assert!(c.is_empty());
+}
@@ -0,0 +1,72 @@
+//! Accuracy of summation algorithms selected by `summation = ...` option
+//!
+//! The inputs are `f32` numbers with 12-bit mantissa, so that their products are exact in `f32`
+//! and the error comes only from the summation.
+//! The reference is computed exactly using integer arithmetic.
+
+use einsum_derive::einsum;
+use ndarray::{Array1, Array2, Array3};
+
+/// Deterministic pseudo random integers in `[0, 2^12)`
+fn mantissas(n: usize, seed: u64) -> Vec<u64> {
+    let mut state = seed;
+    (0..n)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 52
+        })
+        .collect()
+}
+
+const SCALE: f64 = 4096.0; // 2^12
+
+#[test]
+fn error_bound() {
+    let n = 1 << 20;
+    let mx = mantissas(n, 1);
+    let my = mantissas(n, 2);
+    let x: Array1<f32> = mx.iter().map(|&m| (m as f64 / SCALE) as f32).collect();
+    let y: Array1<f32> = my.iter().map(|&m| (m as f64 / SCALE) as f32).collect();
+
+    let exact: u64 = mx.iter().zip(my.iter()).map(|(a, b)| a * b).sum();
+    let exact = exact as f64 / (SCALE * SCALE);
+    let error = |value: f32| ((value as f64 - exact) / exact).abs();
+
+    let sequential = error(einsum!("i,i->", x.view(), y.view()));
+    let kahan = error(einsum!("i,i->", x.view(), y.view(); summation = kahan));
+    let pairwise = error(einsum!("i,i->", x.view(), y.view(); summation = pairwise));
+
+    let eps = f32::EPSILON as f64;
+    // Rounding into f32 is inevitable
+    assert!(kahan <= eps, "kahan = {:e}", kahan);
+    // O(eps log n), with a loose constant
+    assert!(
+        pairwise <= 4.0 * eps * (n as f64).log2(),
+        "pairwise = {:e}",
+        pairwise
+    );
+    assert!(kahan < sequential / 100.0);
+    assert!(pairwise < sequential / 10.0);
+}
+
+#[test]
+fn multiple_contraction_indices() {
+    // Sums of small integers are exact for every algorithm
+    let a = Array3::from_shape_fn((3, 4, 5), |(i, j, k)| (i + 2 * j + 3 * k) as f64);
+    let b = Array2::from_shape_fn((4, 5), |(j, k)| (j * k % 3) as f64);
+    let expected = einsum!("ijk,jk->i", a.view(), b.view());
+    assert_eq!(
+        einsum!("ijk,jk->i", a.view(), b.view(); summation = kahan),
+        expected
+    );
+    assert_eq!(
+        einsum!("ijk,jk->i", a.view(), b.view(); summation = pairwise),
+        expected
+    );
+    assert_eq!(
+        einsum!("ijk,jk->i", a, b; accumulate = f64, summation = pairwise),
+        expected
+    );
+}
@@ -0,0 +1,59 @@
+//! Tiled loops give exactly the same results as untiled ones
+
+use einsum_derive::einsum;
+use ndarray::{Array, Array2, Array3, Array4};
+
+fn value(seed: usize) -> f64 {
+    ((seed * 7919) % 1009) as f64 * 1.3e-3 - 0.5
+}
+
+fn bits<D: ndarray::Dimension>(a: &Array<f64, D>) -> Array<u64, D> {
+    a.mapv(f64::to_bits)
+}
+
+#[test]
+fn matmul() {
+    let a = Array2::from_shape_fn((37, 53), |(i, j)| value(i * 53 + j));
+    let b = Array2::from_shape_fn((53, 29), |(j, k)| value(j * 29 + k + 3));
+    let expected = einsum!("ij,jk->ik", a.view(), b.view());
+    let c = einsum!("ij,jk->ik", a.view(), b.view(); tile = 8);
+    assert_eq!(bits(&c), bits(&expected));
+    let c = einsum!("ij,jk->ik", a.view(), b.view(); tile);
+    assert_eq!(bits(&c), bits(&expected));
+    // Column-major input
+    let a_t = a.t().to_owned();
+    let c = einsum!("ij,jk->ik", a_t.t(), b.view(); tile = 5);
+    assert_eq!(bits(&c), bits(&expected));
+}
+
+#[test]
+fn four_index_transform() {
+    let n = 9;
+    let v = Array4::from_shape_fn((n, n, n, n), |(p, q, r, s)| {
+        value(((p * n + q) * n + r) * n + s)
+    });
+    let c = Array2::from_shape_fn((n, 7), |(p, i)| value(p * 7 + i + 11));
+    let expected = einsum!("pqrs,pi->iqrs", v.view(), c.view());
+    let tiled = einsum!("pqrs,pi->iqrs", v.view(), c.view(); tile = 4);
+    assert_eq!(bits(&tiled), bits(&expected));
+    let tiled = einsum!("pqrs,pi->iqrs", v.view(), c.view(); tile);
+    assert_eq!(bits(&tiled), bits(&expected));
+}
+
+#[test]
+fn multiple_contraction_indices() {
+    let x = Array3::from_shape_fn((6, 13, 11), |(i, j, k)| value(i * 143 + j * 11 + k));
+    let y = Array3::from_shape_fn((13, 11, 5), |(j, k, l)| value(j * 55 + k * 5 + l + 7));
+    let expected = einsum!("ijk,jkl->il", x.view(), y.view());
+    let tiled = einsum!("ijk,jkl->il", x.view(), y.view(); tile = 4);
+    assert_eq!(bits(&tiled), bits(&expected));
+}
+
+#[test]
+fn with_summation() {
+    let x = Array3::from_shape_fn((6, 13, 11), |(i, j, k)| value(i * 143 + j * 11 + k));
+    let y = Array2::from_shape_fn((13, 11), |(j, k)| value(j * 11 + k + 5));
+    let expected = einsum!("ijk,jk->i", x.view(), y.view(); summation = kahan);
+    let tiled = einsum!("ijk,jk->i", x.view(), y.view(); summation = kahan, tile = 4);
+    assert_eq!(bits(&tiled), bits(&expected));
+}
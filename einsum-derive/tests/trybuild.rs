@@ -2,4 +2,8 @@
 fn trybuild() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/cases/number_of_arguments_mismatch.rs");
+    t.compile_fail("tests/cases/rank_mismatch.rs");
+    t.compile_fail("tests/cases/slice_rank_mismatch.rs");
+    t.compile_fail("tests/cases/array_size_mismatch.rs");
+    t.compile_fail("tests/cases/path_size_missing.rs");
 }
@@ -0,0 +1,76 @@
+//! Indexing without bounds checks by `unchecked` option
+//!
+//! Arrays are kept small so that these tests also run under Miri.
+
+use einsum_derive::einsum;
+use ndarray::{array, s, Array2, Array3};
+
+#[test]
+fn matmul() {
+    let a = array![[1, 2, 3], [4, 5, 6]];
+    let b = array![[1, 2], [3, 4], [5, 6]];
+    let expected = einsum!("ij,jk->ik", a.view(), b.view());
+    assert_eq!(expected, array![[22, 28], [49, 64]]);
+    assert_eq!(
+        einsum!("ij,jk->ik", a.view(), b.view(); unchecked),
+        expected
+    );
+
+    // Column-major and negative strides
+    let a_t = a.t().to_owned();
+    assert_eq!(einsum!("ij,jk->ik", a_t.t(), b.view(); unchecked), expected);
+    let b_rev = b.slice(s![..;-1, ..]).to_owned();
+    let b_view = b_rev.slice(s![..;-1, ..]);
+    assert_eq!(einsum!("ij,jk->ik", a.view(), b_view; unchecked), expected);
+}
+
+#[test]
+fn scalar() {
+    let x = array![1.0, 2.0, 3.0];
+    let y = array![4.0, 5.0, 6.0];
+    assert_eq!(einsum!("i,i->", x.view(), y.view(); unchecked), 32.0);
+    assert_eq!(
+        einsum!("i,i->", x.view(), y.view(); unchecked, deterministic),
+        32.0
+    );
+}
+
+#[test]
+fn three_inputs() {
+    let a = Array2::from_shape_fn((3, 4), |(i, j)| (i + j) as i64);
+    let b = Array2::from_shape_fn((4, 2), |(j, k)| (j * k) as i64 - 1);
+    let c = Array2::from_shape_fn((2, 5), |(k, l)| (k + 2 * l) as i64);
+    assert_eq!(
+        einsum!("ij,jk,kl->il", a.view(), b.view(), c.view(); unchecked),
+        einsum!("ij,jk,kl->il", a.view(), b.view(), c.view())
+    );
+}
+
+#[test]
+fn with_options() {
+    let x = Array3::from_shape_fn((3, 4, 5), |(i, j, k)| (i * 20 + j * 5 + k) as f32 * 0.1);
+    let y = Array2::from_shape_fn((4, 5), |(j, k)| (j + k) as f32);
+    let expected = einsum!("ijk,jk->i", x.view(), y.view(); accumulate = f64);
+    assert_eq!(
+        einsum!("ijk,jk->i", x.view(), y.view(); accumulate = f64, unchecked),
+        expected
+    );
+    let expected = einsum!("ijk,jk->i", x.view(), y.view(); summation = pairwise);
+    assert_eq!(
+        einsum!("ijk,jk->i", x.view(), y.view(); summation = pairwise, unchecked),
+        expected
+    );
+    let expected = einsum!("ijk,jk->i", x.view(), y.view(); summation = kahan);
+    assert_eq!(
+        einsum!("ijk,jk->i", x.view(), y.view(); summation = kahan, tile = 2, unchecked),
+        expected
+    );
+}
+
+#[test]
+#[should_panic]
+fn shape_mismatch() {
+    let a = Array2::<f64>::zeros((2, 3));
+    let b = Array2::<f64>::zeros((4, 2));
+    let _ = einsum!("ij,jk->ik", a, b; unchecked);
+}
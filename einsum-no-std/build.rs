@@ -0,0 +1,10 @@
+//! Generate einsum functions by einsum-codegen, see `generated` module
+
+fn main() {
+    einsum_codegen::build::Builder::new()
+        .function("bmm", "bij,bjk->bik")
+        .function("matmul3", "ij,jk,kl->il")
+        .function("trace", "ii->")
+        .write("einsum.rs")
+        .expect("Failed to generate einsum functions");
+}
@@ -0,0 +1,52 @@
+//! Check that the code generated by einsum-derive builds in `no_std` crates
+//!
+//! The functions in [generated] are written by `einsum_codegen::build` in `build.rs`.
+//!
+//! The generated code refers only `core` and `alloc` crates,
+//! and ndarray and num-traits are used without their `std` features.
+//! `alloc` is used only by `einsum_slice!` in the generated code, and declared here for `Vec`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use einsum_derive::{einsum, einsum_array, einsum_slice};
+use ndarray::{Array2, ArrayD, ArrayView1, ArrayView2};
+
+pub mod generated {
+    include!(concat!(env!("OUT_DIR"), "/einsum.rs"));
+}
+
+pub fn matmul(a: ArrayView2<f64>, b: ArrayView2<f64>) -> Array2<f64> {
+    einsum!("ij,jk->ik", a, b)
+}
+
+pub fn dot(x: ArrayView1<i32>, y: ArrayView1<i32>) -> i32 {
+    einsum!("i,i->", x, y)
+}
+
+/// Helper functions of the summation algorithms
+pub fn frobenius(a: ArrayView2<f32>) -> f32 {
+    einsum!("ij,ij->", a, a; accumulate = f64, summation = pairwise, deterministic, tile)
+}
+
+pub fn kahan_trace(a: ArrayView2<f64>) -> f64 {
+    einsum!("ii->", a; summation = kahan)
+}
+
+pub fn matmul_dyn(a: ArrayD<i64>, b: ArrayD<i64>) -> ArrayD<i64> {
+    einsum!("ij,jk->ik", a, b; from_dyn, into_dyn, unchecked)
+}
+
+pub fn matmul_runtime(a: ArrayView2<f64>, b: ArrayView2<f64>) -> Array2<f64> {
+    einsum!("ij,jk->ik", a, b; runtime)
+}
+
+pub fn matmul_slice(a: &[i32], b: &[i32], n: usize) -> Vec<i32> {
+    einsum_slice!("ij,jk->ik", (a, [n, n]), (b, [n, n]))
+}
+
+pub fn rotate(r: [[i32; 3]; 3], x: [i32; 3]) -> [i32; 3] {
+    einsum_array!("ij,j->i", r, x)
+}
@@ -0,0 +1,49 @@
+//! Run the einsum functions defined in the `no_std` crate
+
+use einsum_no_std::*;
+use ndarray::array;
+
+#[test]
+fn ndarray() {
+    let a = array![[1.0, 2.0], [3.0, 4.0]];
+    assert_eq!(
+        matmul(a.view(), a.view()),
+        array![[7.0, 10.0], [15.0, 22.0]]
+    );
+    assert_eq!(
+        matmul_runtime(a.view(), a.view()),
+        matmul(a.view(), a.view())
+    );
+    assert_eq!(kahan_trace(a.view()), 5.0);
+    assert_eq!(frobenius(array![[1.0, 2.0], [3.0, 4.0]].view()), 30.0);
+    assert_eq!(dot(array![1, 2, 3].view(), array![4, 5, 6].view()), 32);
+
+    let b = array![[1, 2], [3, 4]].into_dyn();
+    assert_eq!(
+        matmul_dyn(b.clone(), b),
+        array![[7, 10], [15, 22]].into_dyn()
+    );
+}
+
+#[test]
+fn slice_and_array() {
+    assert_eq!(
+        matmul_slice(&[1, 2, 3, 4], &[1, 0, 0, 1], 2),
+        vec![1, 2, 3, 4]
+    );
+    let r = [[0, -1, 0], [1, 0, 0], [0, 0, 1]];
+    assert_eq!(rotate(r, [1, 2, 3]), [-2, 1, 3]);
+}
+
+#[test]
+fn build_script() {
+    use einsum_no_std::generated::*;
+    let a = array![[1, 2], [3, 4]];
+    assert_eq!(
+        matmul3(a.view(), a.view(), a.view()),
+        array![[37, 54], [81, 118]]
+    );
+    assert_eq!(trace(a.view()), 5);
+    let b = a.clone().into_shape((1, 2, 2)).unwrap();
+    assert_eq!(bmm(b.view(), b.view()), array![[[7, 10], [15, 22]]]);
+}
@@ -0,0 +1,320 @@
+//! Runtime support of [einsum-derive](https://crates.io/crates/einsum-derive)
+//!
+//! `einsum!` defines a function specialized for each step of the contraction path at every call site.
+//! With `runtime` option, it calls [contract] for each step instead,
+//! which is generic over the subscripts and compiled once in this crate:
+//!
+//! ```
+//! use ndarray::array;
+//! use einsum_derive::einsum;
+//!
+//! let a = array![[1.0, 2.0], [3.0, 4.0]];
+//! let c = einsum!("ij,jk->ik", a.view(), a.view(); runtime);
+//! assert_eq!(c, array![[7.0, 10.0], [15.0, 22.0]]);
+//! ```
+//!
+//! Products of two matrices of `f32` or `f64` are computed by [matrixmultiply],
+//! and the other steps by the loops over the indices determined at runtime.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+use core::{
+    any::TypeId,
+    iter::{self, Sum},
+    ops::{Add, Mul},
+};
+use ndarray::{ArrayD, ArrayViewD, IxDyn};
+
+/// Contract the operands along the indices not appearing in `output`
+///
+/// Each operand is given with its subscript, e.g. `"ab"` for a matrix,
+/// and the output is allocated in the row-major order of `output`, e.g. `"ac"`.
+/// A repeated index in a subscript, e.g. `"aa"`, takes the diagonal elements.
+///
+/// ```
+/// use ndarray::array;
+///
+/// let a = array![[1.0, 2.0], [3.0, 4.0]].into_dyn();
+/// let c = einsum_runtime::contract(&[("ab", a.view()), ("bc", a.view())], "ac");
+/// assert_eq!(c, array![[7.0, 10.0], [15.0, 22.0]].into_dyn());
+///
+/// let trace = einsum_runtime::contract(&[("aa", a.view())], "");
+/// assert_eq!(trace.sum(), 5.0);
+/// ```
+///
+/// # Panics
+///
+/// - The number of axes of an operand differs from the length of its subscript
+/// - The sizes of an index differ among the operands
+/// - An index of `output` does not appear in the operands, or `operands` is empty
+pub fn contract<T>(operands: &[(&str, ArrayViewD<'_, T>)], output: &str) -> ArrayD<T>
+where
+    T: Clone + Sum + Add<Output = T> + Mul<Output = T> + 'static,
+{
+    assert!(!operands.is_empty(), "einsum: no operands");
+    let indices = Indices::new(operands, output);
+    // Zero as the sum of no elements
+    let zero = T::sum(iter::empty());
+    let mut out = ArrayD::from_elem(IxDyn(&indices.sizes[..indices.n_output]), zero);
+    if indices.sizes.contains(&0) {
+        return out;
+    }
+    if !gemm(operands, &indices, &mut out) {
+        loops(operands, &indices, &mut out);
+    }
+    out
+}
+
+/// Indices of [contract], where the output indices come first and the contraction ones follow
+struct Indices {
+    /// Characters of the output indices followed by the contraction ones
+    chars: Vec<char>,
+    /// Size of each index
+    sizes: Vec<usize>,
+    /// Number of the output indices
+    n_output: usize,
+    /// Stride of each operand along each index, which is zero if the index does not appear
+    strides: Vec<Vec<isize>>,
+}
+
+impl Indices {
+    fn new<T>(operands: &[(&str, ArrayViewD<'_, T>)], output: &str) -> Self {
+        let mut chars: Vec<char> = output.chars().collect();
+        for (subscript, _) in operands {
+            for c in subscript.chars() {
+                if !chars.contains(&c) {
+                    chars.push(c);
+                }
+            }
+        }
+        let mut sizes: Vec<Option<usize>> = vec![None; chars.len()];
+        let mut strides = Vec::new();
+        for (subscript, array) in operands {
+            let rank = subscript.chars().count();
+            assert_eq!(
+                array.ndim(),
+                rank,
+                "einsum: operand of subscript `{}` has {} axes, but its subscript requires {}",
+                subscript,
+                array.ndim(),
+                rank
+            );
+            let mut operand_strides = vec![0; chars.len()];
+            for ((c, &n), &stride) in subscript.chars().zip(array.shape()).zip(array.strides()) {
+                let k = chars.iter().position(|&x| x == c).unwrap();
+                match sizes[k] {
+                    Some(size) => assert_eq!(
+                        size, n,
+                        "einsum: size of index `{}` mismatches: {} and {}",
+                        c, size, n
+                    ),
+                    None => sizes[k] = Some(n),
+                }
+                operand_strides[k] += stride;
+            }
+            strides.push(operand_strides);
+        }
+        let sizes: Vec<usize> = sizes
+            .into_iter()
+            .zip(&chars)
+            .map(|(size, c)| {
+                size.unwrap_or_else(|| {
+                    panic!(
+                        "einsum: output index `{}` does not appear in the operands",
+                        c
+                    )
+                })
+            })
+            .collect();
+        Indices {
+            chars,
+            sizes,
+            n_output: output.chars().count(),
+            strides,
+        }
+    }
+}
+
+/// Sum up the products over all indices, where the last index is iterated fastest
+fn loops<T>(operands: &[(&str, ArrayViewD<'_, T>)], indices: &Indices, out: &mut ArrayD<T>)
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+{
+    let n = indices.sizes.len();
+    let out_strides: Vec<isize> = (0..n)
+        .map(|k| out.strides().get(k).cloned().unwrap_or(0))
+        .collect();
+    let inputs: Vec<*const T> = operands.iter().map(|(_, array)| array.as_ptr()).collect();
+    let output = out.as_mut_ptr();
+    let mut counter = vec![0; n];
+    let mut offsets = vec![0isize; operands.len()];
+    let mut out_offset = 0isize;
+    loop {
+        // SAFETY: Each index is less than its size, which is the shape of the arrays along it,
+        // so that the offsets stay in the arrays
+        unsafe {
+            let mut prod = (*inputs[0].offset(offsets[0])).clone();
+            for (input, offset) in inputs.iter().zip(&offsets).skip(1) {
+                prod = prod * (*input.offset(*offset)).clone();
+            }
+            let elem = output.offset(out_offset);
+            *elem = (*elem).clone() + prod;
+        }
+        // Increment the counter from the last index
+        let mut k = n;
+        loop {
+            if k == 0 {
+                return;
+            }
+            k -= 1;
+            counter[k] += 1;
+            for (offset, strides) in offsets.iter_mut().zip(&indices.strides) {
+                *offset += strides[k];
+            }
+            out_offset += out_strides[k];
+            if counter[k] < indices.sizes[k] {
+                break;
+            }
+            let size = indices.sizes[k] as isize;
+            for (offset, strides) in offsets.iter_mut().zip(&indices.strides) {
+                *offset -= strides[k] * size;
+            }
+            out_offset -= out_strides[k] * size;
+            counter[k] = 0;
+        }
+    }
+}
+
+/// Product of two matrices by [matrixmultiply] for `f32` and `f64`,
+/// e.g. `ab,bc->ac` and `ba,cb->ca`, returns `false` for the others
+fn gemm<T: 'static>(
+    operands: &[(&str, ArrayViewD<'_, T>)],
+    indices: &Indices,
+    out: &mut ArrayD<T>,
+) -> bool {
+    let is_f32 = TypeId::of::<T>() == TypeId::of::<f32>();
+    let is_f64 = TypeId::of::<T>() == TypeId::of::<f64>();
+    if !(is_f32 || is_f64)
+        || operands.len() != 2
+        || indices.n_output != 2
+        || indices.sizes.len() != 3
+    {
+        return false;
+    }
+    // Output indices `i`, `k` and contraction index `l`
+    let (i, k, l) = (0, 1, 2);
+    let is_matrix = |subscript: &str, row: usize| {
+        subscript.chars().count() == 2
+            && subscript.contains(indices.chars[row])
+            && subscript.contains(indices.chars[l])
+    };
+    let ((_, a), (_, b)) = (&operands[0], &operands[1]);
+    let (sa, sb) = (&indices.strides[0], &indices.strides[1]);
+    let (a, b, sa, sb) = if is_matrix(operands[0].0, i) && is_matrix(operands[1].0, k) {
+        (a, b, sa, sb)
+    } else if is_matrix(operands[0].0, k) && is_matrix(operands[1].0, i) {
+        (b, a, sb, sa)
+    } else {
+        return false;
+    };
+    let (m, n, p) = (indices.sizes[i], indices.sizes[k], indices.sizes[l]);
+    let (rsc, csc) = (out.strides()[0], out.strides()[1]);
+    // SAFETY: `T` is `f32` or `f64` checked by `TypeId`,
+    // and the strides are of the arrays of the sizes
+    unsafe {
+        if is_f64 {
+            matrixmultiply::dgemm(
+                m,
+                p,
+                n,
+                1.0,
+                a.as_ptr() as *const f64,
+                sa[i],
+                sa[l],
+                b.as_ptr() as *const f64,
+                sb[l],
+                sb[k],
+                0.0,
+                out.as_mut_ptr() as *mut f64,
+                rsc,
+                csc,
+            );
+        } else {
+            matrixmultiply::sgemm(
+                m,
+                p,
+                n,
+                1.0,
+                a.as_ptr() as *const f32,
+                sa[i],
+                sa[l],
+                b.as_ptr() as *const f32,
+                sb[l],
+                sb[k],
+                0.0,
+                out.as_mut_ptr() as *mut f32,
+                rsc,
+                csc,
+            );
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ndarray::{Array, Array2, ShapeBuilder};
+
+    fn matrix<T: From<u8>>(shape: (usize, usize)) -> ArrayD<T> {
+        let (n, m) = shape;
+        Array::from_shape_fn((n, m), |(i, j)| T::from((i * m + j) as u8 % 7)).into_dyn()
+    }
+
+    #[test]
+    fn gemm_and_loops() {
+        let a = matrix::<f64>((3, 4));
+        let b = matrix::<f64>((4, 5));
+        let c = contract(&[("ab", a.view()), ("bc", b.view())], "ac");
+        // Same contraction by the loops for integers
+        let ai = matrix::<i64>((3, 4));
+        let bi = matrix::<i64>((4, 5));
+        let ci = contract(&[("ab", ai.view()), ("bc", bi.view())], "ac");
+        assert_eq!(c, ci.mapv(|x| x as f64));
+
+        // Transposed operands and output
+        let bt = b.t().to_owned();
+        let ct = contract(&[("cb", bt.view()), ("ab", a.view())], "ca");
+        assert_eq!(ct.t(), c);
+
+        // Column-major output layout of the input does not matter
+        let af: Array2<f32> = Array::from_shape_fn((3, 4).f(), |(i, j)| ((i * 4 + j) % 7) as f32);
+        let bf = matrix::<f32>((4, 5));
+        let cf = contract(&[("ab", af.view().into_dyn()), ("bc", bf.view())], "ac");
+        assert_eq!(cf, c.mapv(|x| x as f32));
+    }
+
+    #[test]
+    fn reductions() {
+        let a = matrix::<i32>((3, 3));
+        let trace = contract(&[("aa", a.view())], "");
+        // 0 + 4 + 8 % 7
+        assert_eq!(trace.sum(), 5);
+        let total = contract(&[("ab", a.view())], "");
+        assert_eq!(total.sum(), a.sum());
+        let diag = contract(&[("aa", a.view())], "a");
+        assert_eq!(diag, a.diag().to_owned().into_dyn());
+        let empty = contract(&[("ab", matrix::<i32>((0, 3)).view())], "b");
+        assert_eq!(empty, Array::zeros(3).into_dyn());
+    }
+
+    #[test]
+    #[should_panic(expected = "einsum: size of index `b` mismatches: 4 and 3")]
+    fn size_mismatch() {
+        let a = matrix::<f64>((3, 4));
+        let _ = contract(&[("ab", a.view()), ("bc", a.view())], "ac");
+    }
+}